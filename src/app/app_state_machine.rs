@@ -1,6 +1,11 @@
 use bevy::prelude::*;
 use bevy_asset_loader::loading_state::{LoadingState, LoadingStateAppExt};
 
+use super::{
+    game_levels::CurrentLevel,
+    transition_table::{InvalidTransition, TransitionTable},
+};
+
 pub struct AppStateMachinePlugin;
 
 impl Plugin for AppStateMachinePlugin {
@@ -10,7 +15,9 @@ impl Plugin for AppStateMachinePlugin {
                 LoadingState::new(AppState::AssetLoading).continue_to_state(AppState::MainMenu),
             )
             .add_event::<AppTransitionEvent>()
-            .add_systems(Update, app_transition);
+            .add_event::<InvalidTransition<AppState, AppTransitionKind>>()
+            .add_systems(Update, app_transition)
+            .add_systems(OnEnter(AppState::ReloadingLevel), reload_level);
     }
 }
 
@@ -46,6 +53,10 @@ impl<S: States> StateStack<S> {
     pub fn pop(&mut self) -> Option<S> {
         self.queue.pop_back()
     }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
@@ -64,62 +75,443 @@ pub enum AppState {
 
     /// The user is in the game
     InGameLevel,
+
+    /// Passed through for a single frame when restarting or changing levels
+    /// so that the level is torn down and spawned again
+    ReloadingLevel,
 }
 
-#[derive(Event)]
+#[derive(Event, Debug)]
 pub enum AppTransitionEvent {
+    /// Resume the last level played
     Continue,
 
+    /// Go to the level selection screen if `None`, otherwise play the level
+    /// at the given index
     SelectLevel(Option<usize>),
 
+    /// Restart the current level
     Retry,
 
+    /// Play the level at the given index
     NextLevel(usize),
 
     Settings,
 
     GoBack,
+
+    /// Leave the current level for the main menu
+    QuitToMenu,
 }
 
+/// [`AppTransitionEvent`] without its payload, used to look up transitions in
+/// [`APP_TRANSITIONS`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AppTransitionKind {
+    Continue,
+    OpenLevelSelection,
+    SelectLevel,
+    Retry,
+    NextLevel,
+    Settings,
+    GoBack,
+    QuitToMenu,
+}
+
+impl AppTransitionEvent {
+    pub fn kind(&self) -> AppTransitionKind {
+        match self {
+            AppTransitionEvent::Continue => AppTransitionKind::Continue,
+            AppTransitionEvent::SelectLevel(None) => AppTransitionKind::OpenLevelSelection,
+            AppTransitionEvent::SelectLevel(Some(_)) => AppTransitionKind::SelectLevel,
+            AppTransitionEvent::Retry => AppTransitionKind::Retry,
+            AppTransitionEvent::NextLevel(_) => AppTransitionKind::NextLevel,
+            AppTransitionEvent::Settings => AppTransitionKind::Settings,
+            AppTransitionEvent::GoBack => AppTransitionKind::GoBack,
+            AppTransitionEvent::QuitToMenu => AppTransitionKind::QuitToMenu,
+        }
+    }
+}
+
+/// Every legal transition of [`AppState`]. For [`AppTransitionKind::GoBack`]
+/// the target is only used when there's no previous state to go back to.
+pub static APP_TRANSITIONS: TransitionTable<AppState, AppTransitionKind> = TransitionTable::new(&[
+    // Main Menu Transitions
+    (
+        AppState::MainMenu,
+        AppTransitionKind::Continue,
+        AppState::InGameLevel,
+    ),
+    (
+        AppState::MainMenu,
+        AppTransitionKind::OpenLevelSelection,
+        AppState::LevelSelection,
+    ),
+    (
+        AppState::MainMenu,
+        AppTransitionKind::Settings,
+        AppState::Settings,
+    ),
+    // Settings Transitions
+    (
+        AppState::Settings,
+        AppTransitionKind::GoBack,
+        AppState::MainMenu,
+    ),
+    // Level Selection Transitions
+    (
+        AppState::LevelSelection,
+        AppTransitionKind::SelectLevel,
+        AppState::InGameLevel,
+    ),
+    (
+        AppState::LevelSelection,
+        AppTransitionKind::GoBack,
+        AppState::MainMenu,
+    ),
+    // In Game Transitions
+    // We're already in game so we need to go through the reloading state for
+    // the level to be torn down and spawned again.
+    (
+        AppState::InGameLevel,
+        AppTransitionKind::NextLevel,
+        AppState::ReloadingLevel,
+    ),
+    (
+        AppState::InGameLevel,
+        AppTransitionKind::Retry,
+        AppState::ReloadingLevel,
+    ),
+    (
+        AppState::InGameLevel,
+        AppTransitionKind::Settings,
+        AppState::Settings,
+    ),
+    (
+        AppState::InGameLevel,
+        AppTransitionKind::GoBack,
+        AppState::MainMenu,
+    ),
+    (
+        AppState::InGameLevel,
+        AppTransitionKind::QuitToMenu,
+        AppState::MainMenu,
+    ),
+]);
+
 fn app_transition(
     mut previous_state: Local<StateStack<AppState>>,
     current_state: Res<State<AppState>>,
     mut next_state: ResMut<NextState<AppState>>,
+    mut current_level: ResMut<CurrentLevel>,
     mut transition_event_reader: EventReader<AppTransitionEvent>,
+    mut invalid_transition_writer: EventWriter<InvalidTransition<AppState, AppTransitionKind>>,
 ) {
+    // Several events can arrive in the same frame (e.g. a double click) so we
+    // validate each one against the state the previous one would have led to.
+    let mut from = current_state.get().clone();
+
     for transition_event in transition_event_reader.iter() {
-        let next_queued = match (current_state.clone(), transition_event) {
-            // Main Menu Transitions
-            (AppState::MainMenu, AppTransitionEvent::Continue) => {
-                // TODO: Get the last level played and pass it through
-                AppState::InGameLevel
-            }
-            (AppState::MainMenu, AppTransitionEvent::SelectLevel(None)) => AppState::LevelSelection,
-            (AppState::MainMenu, AppTransitionEvent::Settings) => AppState::Settings,
+        let kind = transition_event.kind();
 
-            // Settings Transitions
+        let Some(target) = APP_TRANSITIONS.target(&from, &kind) else {
+            let invalid_transition = InvalidTransition {
+                from: from.clone(),
+                kind,
+            };
+            warn!(
+                "Ignoring invalid app transition {:?} from {:?}",
+                invalid_transition.kind, invalid_transition.from
+            );
+            invalid_transition_writer.send(invalid_transition);
+            continue;
+        };
 
-            // Level Selection Transitions
-            (AppState::LevelSelection, AppTransitionEvent::SelectLevel(_level)) => {
-                // TODO: Pass through the level selected
-                AppState::InGameLevel
+        match transition_event {
+            AppTransitionEvent::SelectLevel(Some(level)) | AppTransitionEvent::NextLevel(level) => {
+                current_level.0 = *level;
             }
+            _ => {}
+        }
 
-            // In Game Transitions
-            (AppState::InGameLevel, AppTransitionEvent::NextLevel(_level)) => {
-                // TODO: Pass through the level selected
-                AppState::InGameLevel
+        let next_queued = match kind {
+            AppTransitionKind::GoBack => previous_state.pop().unwrap_or_else(|| target.clone()),
+            // There's nothing to go back to from the main menu
+            AppTransitionKind::QuitToMenu => {
+                previous_state.clear();
+                target.clone()
             }
-            (AppState::InGameLevel, AppTransitionEvent::Retry) => AppState::InGameLevel,
+            _ => {
+                // We don't want to be able to go back to a level we just left
+                if *target != AppState::ReloadingLevel {
+                    previous_state.push(from.clone());
+                }
 
-            // Default Transitions
-            // TODO: Curious if this will cause a loop where previous is what
-            // previous was previously, lol
-            (_, AppTransitionEvent::GoBack) => previous_state.pop().unwrap_or(AppState::MainMenu),
-            _ => panic!("Invalid state transition"),
+                target.clone()
+            }
         };
 
-        previous_state.push(current_state.clone());
-        next_state.0 = Some(next_queued);
+        next_state.set(next_queued.clone());
+        from = next_queued;
+    }
+}
+
+fn reload_level(mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::InGameLevel);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATES: [AppState; 6] = [
+        AppState::AssetLoading,
+        AppState::MainMenu,
+        AppState::Settings,
+        AppState::LevelSelection,
+        AppState::InGameLevel,
+        AppState::ReloadingLevel,
+    ];
+
+    const KINDS: [AppTransitionKind; 8] = [
+        AppTransitionKind::Continue,
+        AppTransitionKind::OpenLevelSelection,
+        AppTransitionKind::SelectLevel,
+        AppTransitionKind::Retry,
+        AppTransitionKind::NextLevel,
+        AppTransitionKind::Settings,
+        AppTransitionKind::GoBack,
+        AppTransitionKind::QuitToMenu,
+    ];
+
+    /// Written out by hand rather than read back from [`APP_TRANSITIONS`] so
+    /// that changing the table has to change this too.
+    static EXPECTED: [(AppState, AppTransitionKind, AppState); 11] = [
+        (
+            AppState::MainMenu,
+            AppTransitionKind::Continue,
+            AppState::InGameLevel,
+        ),
+        (
+            AppState::MainMenu,
+            AppTransitionKind::OpenLevelSelection,
+            AppState::LevelSelection,
+        ),
+        (
+            AppState::MainMenu,
+            AppTransitionKind::Settings,
+            AppState::Settings,
+        ),
+        (
+            AppState::Settings,
+            AppTransitionKind::GoBack,
+            AppState::MainMenu,
+        ),
+        (
+            AppState::LevelSelection,
+            AppTransitionKind::SelectLevel,
+            AppState::InGameLevel,
+        ),
+        (
+            AppState::LevelSelection,
+            AppTransitionKind::GoBack,
+            AppState::MainMenu,
+        ),
+        (
+            AppState::InGameLevel,
+            AppTransitionKind::NextLevel,
+            AppState::ReloadingLevel,
+        ),
+        (
+            AppState::InGameLevel,
+            AppTransitionKind::Retry,
+            AppState::ReloadingLevel,
+        ),
+        (
+            AppState::InGameLevel,
+            AppTransitionKind::Settings,
+            AppState::Settings,
+        ),
+        (
+            AppState::InGameLevel,
+            AppTransitionKind::GoBack,
+            AppState::MainMenu,
+        ),
+        (
+            AppState::InGameLevel,
+            AppTransitionKind::QuitToMenu,
+            AppState::MainMenu,
+        ),
+    ];
+
+    fn expected_target(from: &AppState, kind: &AppTransitionKind) -> Option<&'static AppState> {
+        EXPECTED
+            .iter()
+            .find(|(edge_from, edge_kind, _)| edge_from == from && edge_kind == kind)
+            .map(|(_, _, to)| to)
+    }
+
+    /// An event of the given kind, with whatever payload
+    fn event_of(kind: AppTransitionKind) -> AppTransitionEvent {
+        match kind {
+            AppTransitionKind::Continue => AppTransitionEvent::Continue,
+            AppTransitionKind::OpenLevelSelection => AppTransitionEvent::SelectLevel(None),
+            AppTransitionKind::SelectLevel => AppTransitionEvent::SelectLevel(Some(1)),
+            AppTransitionKind::Retry => AppTransitionEvent::Retry,
+            AppTransitionKind::NextLevel => AppTransitionEvent::NextLevel(1),
+            AppTransitionKind::Settings => AppTransitionEvent::Settings,
+            AppTransitionKind::GoBack => AppTransitionEvent::GoBack,
+            AppTransitionKind::QuitToMenu => AppTransitionEvent::QuitToMenu,
+        }
+    }
+
+    #[test]
+    fn table_matches_expected_edges() {
+        for from in &STATES {
+            for kind in &KINDS {
+                assert_eq!(
+                    APP_TRANSITIONS.target(from, kind),
+                    expected_target(from, kind),
+                    "{:?} from {:?}",
+                    kind,
+                    from
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_edge_is_listed_once() {
+        let edges = APP_TRANSITIONS.edges();
+
+        for (index, (from, kind, _)) in edges.iter().enumerate() {
+            assert!(
+                !edges[index + 1..]
+                    .iter()
+                    .any(|(other_from, other_kind, _)| other_from == from && other_kind == kind),
+                "{:?} from {:?} is listed twice",
+                kind,
+                from
+            );
+        }
+
+        assert_eq!(edges.len(), EXPECTED.len());
+    }
+
+    fn app_in(state: AppState) -> App {
+        let mut app = App::new();
+        app.add_state::<AppState>()
+            .init_resource::<CurrentLevel>()
+            .add_event::<AppTransitionEvent>()
+            .add_event::<InvalidTransition<AppState, AppTransitionKind>>()
+            .add_systems(Update, app_transition);
+
+        app.world.insert_resource(NextState(Some(state)));
+        app.update();
+
+        app
+    }
+
+    /// Sends the event and gives `apply_state_transition` a frame to apply
+    /// the next state
+    fn transition(app: &mut App, event: AppTransitionEvent) {
+        app.world.send_event(event);
+        app.update();
+        app.update();
+    }
+
+    fn state(app: &App) -> &AppState {
+        app.world.resource::<State<AppState>>().get()
+    }
+
+    fn invalid_transitions(app: &App) -> Vec<(AppState, AppTransitionKind)> {
+        let events = app
+            .world
+            .resource::<Events<InvalidTransition<AppState, AppTransitionKind>>>();
+
+        events
+            .get_reader()
+            .iter(events)
+            .map(|invalid_transition| (invalid_transition.from.clone(), invalid_transition.kind))
+            .collect()
+    }
+
+    #[test]
+    fn dispatcher_takes_every_edge() {
+        for (from, kind, to) in &EXPECTED {
+            let mut app = app_in(from.clone());
+
+            transition(&mut app, event_of(*kind));
+
+            assert_eq!(state(&app), to, "{:?} from {:?}", kind, from);
+            assert!(invalid_transitions(&app).is_empty());
+        }
+    }
+
+    #[test]
+    fn dispatcher_carries_the_selected_level() {
+        let mut app = app_in(AppState::LevelSelection);
+
+        transition(&mut app, AppTransitionEvent::SelectLevel(Some(2)));
+        assert_eq!(state(&app), &AppState::InGameLevel);
+        assert_eq!(app.world.resource::<CurrentLevel>().0, 2);
+
+        transition(&mut app, AppTransitionEvent::NextLevel(3));
+        assert_eq!(state(&app), &AppState::ReloadingLevel);
+        assert_eq!(app.world.resource::<CurrentLevel>().0, 3);
+    }
+
+    #[test]
+    fn retry_reloads_through_the_reloading_state() {
+        let mut app = app_in(AppState::InGameLevel);
+        app.add_systems(OnEnter(AppState::ReloadingLevel), reload_level);
+
+        transition(&mut app, AppTransitionEvent::Retry);
+        assert_eq!(state(&app), &AppState::ReloadingLevel);
+
+        app.update();
+        assert_eq!(state(&app), &AppState::InGameLevel);
+    }
+
+    #[test]
+    fn go_back_returns_to_the_previous_state() {
+        let mut app = app_in(AppState::InGameLevel);
+
+        transition(&mut app, AppTransitionEvent::Settings);
+        assert_eq!(state(&app), &AppState::Settings);
+
+        // The table says the main menu but we came from the level
+        transition(&mut app, AppTransitionEvent::GoBack);
+        assert_eq!(state(&app), &AppState::InGameLevel);
+
+        assert!(invalid_transitions(&app).is_empty());
+    }
+
+    #[test]
+    fn quitting_and_continuing() {
+        let mut app = app_in(AppState::MainMenu);
+
+        transition(&mut app, AppTransitionEvent::SelectLevel(None));
+        transition(&mut app, AppTransitionEvent::SelectLevel(Some(0)));
+        transition(&mut app, AppTransitionEvent::QuitToMenu);
+        assert_eq!(state(&app), &AppState::MainMenu);
+
+        transition(&mut app, AppTransitionEvent::Continue);
+        assert_eq!(state(&app), &AppState::InGameLevel);
+
+        assert!(invalid_transitions(&app).is_empty());
+    }
+
+    #[test]
+    fn dispatcher_rejects_illegal_transitions() {
+        let mut app = app_in(AppState::Settings);
+
+        transition(&mut app, AppTransitionEvent::Retry);
+
+        assert_eq!(state(&app), &AppState::Settings);
+        assert_eq!(
+            invalid_transitions(&app),
+            vec![(AppState::Settings, AppTransitionKind::Retry)]
+        );
     }
 }
@@ -0,0 +1,186 @@
+//! Black holes pull bodies in like any other point of gravity, stretch them
+//! as they get close and swallow them once they cross the event horizon.
+
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+
+use super::{
+    game_state_machine::GameState,
+    gravity::GravityBound,
+    junk::Junk,
+    player::Player,
+    void::{PlayerLostEvent, VoidSystemSet},
+};
+
+pub struct BlackHolePlugin;
+
+impl Plugin for BlackHolePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BlackHole>()
+            .register_type::<Swallow>()
+            .add_event::<SwallowedEvent>()
+            .add_systems(
+                Update,
+                (stretch_bodies, swallow_bodies)
+                    .chain()
+                    .run_if(in_state(GameState::Playing))
+                    .before(VoidSystemSet),
+            );
+    }
+}
+
+/// How much longer than they are wide bodies get right at the event horizon
+const MAX_STRETCH: f32 = 3.0;
+
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct BlackHole {
+    /// Bodies closer than this to the center are swallowed
+    pub event_horizon: f32,
+
+    /// Bodies closer than this to the center start getting stretched
+    pub stretch_radius: f32,
+
+    pub swallow: Swallow,
+}
+
+/// What happens to bodies that cross the event horizon. Players are always
+/// lost in space, which respawns them or fails the level.
+#[derive(Reflect, Default, Debug, Clone, Copy, PartialEq)]
+pub enum Swallow {
+    #[default]
+    Despawn,
+
+    /// Comes out at the given position, e.g. a white hole
+    Teleport(Vec3),
+}
+
+/// Sent whenever a black hole swallows a body.
+#[derive(Event)]
+pub struct SwallowedEvent {
+    pub black_hole: Entity,
+
+    pub body: Entity,
+
+    /// Whether the body was junk, as it may be gone by the time this is read
+    pub junk: bool,
+}
+
+/// A body being stretched by a black hole, and the scale to go back to once
+/// it's out of reach.
+#[derive(Component, Debug)]
+pub struct Stretched {
+    pub original_scale: Vec3,
+}
+
+fn stretch_bodies(
+    mut commands: Commands,
+    black_hole_query: Query<(&Position, &BlackHole)>,
+    mut body_query: Query<
+        (
+            Entity,
+            &Position,
+            &Rotation,
+            &mut Transform,
+            Option<&Stretched>,
+        ),
+        (With<GravityBound>, Without<BlackHole>),
+    >,
+) {
+    for (entity, position, rotation, mut transform, stretched) in &mut body_query {
+        // How close the body is to the event horizon of the nearest black
+        // hole, from 0 at the edge of its reach to 1 at the horizon
+        let closest = black_hole_query
+            .iter()
+            .filter_map(|(black_hole_position, black_hole)| {
+                let distance = position.0.distance(black_hole_position.0);
+                let reach = black_hole.stretch_radius - black_hole.event_horizon;
+
+                (distance < black_hole.stretch_radius && reach > 0.0).then(|| {
+                    let closeness = 1.0 - (distance - black_hole.event_horizon) / reach;
+                    (black_hole_position.0, closeness.clamp(0.0, 1.0))
+                })
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        let Some((black_hole_position, closeness)) = closest else {
+            if let Some(stretched) = stretched {
+                transform.scale = stretched.original_scale;
+                commands.entity(entity).remove::<Stretched>();
+            }
+            continue;
+        };
+
+        let original_scale = match stretched {
+            Some(stretched) => stretched.original_scale,
+            None => {
+                commands.entity(entity).insert(Stretched {
+                    original_scale: transform.scale,
+                });
+                transform.scale
+            }
+        };
+
+        // Long along the direction to the black hole and thin across it,
+        // keeping roughly the same volume
+        let towards = (rotation.0.inverse() * (black_hole_position - position.0))
+            .normalize_or_zero()
+            .abs();
+        let stretch = 1.0 + closeness * (MAX_STRETCH - 1.0);
+        let thin = 1.0 / stretch.sqrt();
+
+        transform.scale = original_scale * (Vec3::splat(thin) + towards * (stretch - thin));
+    }
+}
+
+fn swallow_bodies(
+    mut commands: Commands,
+    black_hole_query: Query<(Entity, &Position, &BlackHole)>,
+    mut body_query: Query<
+        (
+            Entity,
+            &mut Position,
+            &mut LinearVelocity,
+            &mut AngularVelocity,
+            Option<&Player>,
+            Option<&Junk>,
+        ),
+        (With<GravityBound>, Without<BlackHole>),
+    >,
+    mut swallowed_writer: EventWriter<SwallowedEvent>,
+    mut player_lost_writer: EventWriter<PlayerLostEvent>,
+) {
+    for (body, mut position, mut linear_velocity, mut angular_velocity, player, junk) in
+        &mut body_query
+    {
+        let Some((black_hole_entity, black_hole)) = black_hole_query
+            .iter()
+            .find(|(_, black_hole_position, black_hole)| {
+                position.0.distance(black_hole_position.0) <= black_hole.event_horizon
+            })
+            .map(|(entity, _, black_hole)| (entity, black_hole))
+        else {
+            continue;
+        };
+
+        swallowed_writer.send(SwallowedEvent {
+            black_hole: black_hole_entity,
+            body,
+            junk: junk.is_some(),
+        });
+
+        if player.is_some() {
+            player_lost_writer.send(PlayerLostEvent { player: body });
+            continue;
+        }
+
+        match black_hole.swallow {
+            Swallow::Despawn => commands.entity(body).despawn_recursive(),
+            Swallow::Teleport(destination) => {
+                position.0 = destination;
+                linear_velocity.0 = Vec3::ZERO;
+                angular_velocity.0 = Vec3::ZERO;
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Fields that push bodies around without being gravity. They're driven by
+//! sensors just like gravity sources but don't count towards a body's
+//! [`GravityBound`](super::gravity::GravityBound), so they never change which
+//! way is up.
+
+use bevy::prelude::*;
+use bevy_xpbd_3d::{prelude::*, PhysicsSchedule};
+
+use super::{
+    game_state_machine::GameState,
+    gravity::{GravityFalloff, GravitySystemSet},
+    DebugGizmos,
+};
+
+pub struct ForceFieldsPlugin;
+
+impl Plugin for ForceFieldsPlugin {
+    fn build(&self, app: &mut App) {
+        use bevy_trait_query::RegisterExt;
+
+        app.register_type::<Wind>()
+            .register_type::<Vortex>()
+            .register_type::<Drag>()
+            .register_type::<Repulsor>()
+            .register_component_as::<dyn ForceField, Wind>()
+            .register_component_as::<dyn ForceField, Vortex>()
+            .register_component_as::<dyn ForceField, Drag>()
+            .register_component_as::<dyn ForceField, Repulsor>()
+            .add_systems(
+                PhysicsSchedule,
+                apply_force_fields
+                    .run_if(in_state(GameState::Playing))
+                    .in_set(GravitySystemSet),
+            );
+    }
+}
+
+/// The state of a body inside a force field.
+pub struct FieldBody {
+    pub position: Vec3,
+
+    pub velocity: Vec3,
+
+    pub mass: f32,
+}
+
+#[bevy_trait_query::queryable]
+pub trait ForceField {
+    fn calculate_force(&self, position: Vec3, body: &FieldBody) -> Vec3;
+}
+
+/// Blows bodies along a direction, e.g. jet streams and solar wind.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+pub struct Wind {
+    pub direction: Vec3,
+
+    pub strength: f32,
+}
+
+impl ForceField for Wind {
+    fn calculate_force(&self, _position: Vec3, body: &FieldBody) -> Vec3 {
+        self.direction.normalize_or_zero() * self.strength * body.mass
+    }
+}
+
+/// Swirls bodies around an axis through the field.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+pub struct Vortex {
+    pub axis: Vec3,
+
+    /// How hard bodies are pushed around the axis
+    pub strength: f32,
+
+    /// How hard bodies are pulled in towards the axis
+    pub pull: f32,
+}
+
+impl ForceField for Vortex {
+    fn calculate_force(&self, position: Vec3, body: &FieldBody) -> Vec3 {
+        let axis = self.axis.normalize_or_zero();
+        let offset = body.position - position;
+
+        // Only the part of the offset perpendicular to the axis matters
+        let radial = offset - axis * offset.dot(axis);
+        let tangent = axis.cross(radial).normalize_or_zero();
+
+        (tangent * self.strength - radial.normalize_or_zero() * self.pull) * body.mass
+    }
+}
+
+/// Slows bodies down, like an atmosphere.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+pub struct Drag {
+    /// The fraction of a body's velocity that's taken away each second
+    pub coefficient: f32,
+}
+
+impl ForceField for Drag {
+    fn calculate_force(&self, _position: Vec3, body: &FieldBody) -> Vec3 {
+        -body.velocity * self.coefficient * body.mass
+    }
+}
+
+/// Pushes bodies away from the field's center, e.g. launchers.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+pub struct Repulsor {
+    pub strength: f32,
+
+    pub falloff: GravityFalloff,
+}
+
+impl ForceField for Repulsor {
+    fn calculate_force(&self, position: Vec3, body: &FieldBody) -> Vec3 {
+        let offset = body.position - position;
+
+        offset.normalize_or_zero()
+            * self.strength
+            * body.mass
+            * self.falloff.factor(offset.length())
+    }
+}
+
+fn apply_force_fields(
+    debug_gizmos: Res<DebugGizmos>,
+    mut gizmos: Gizmos,
+    mut rigid_body_query: Query<
+        (RigidBodyQuery, &mut ExternalForce, &CollidingEntities),
+        Without<Sensor>,
+    >,
+    force_field_query: Query<(&dyn ForceField, &Position), With<Sensor>>,
+) {
+    for (rb_item, mut external_force, colliding_entities) in rigid_body_query.iter_mut() {
+        if !rb_item.rb.is_dynamic() {
+            continue;
+        }
+
+        let body = FieldBody {
+            position: rb_item.position.0,
+            velocity: rb_item.linear_velocity.0,
+            mass: rb_item.mass.0,
+        };
+
+        for colliding_entity in colliding_entities.0.iter() {
+            let Ok((force_fields, position)) = force_field_query.get(*colliding_entity) else {
+                continue;
+            };
+
+            for force_field in force_fields {
+                let force = force_field.calculate_force(position.0, &body);
+
+                external_force.apply_force(force);
+
+                if debug_gizmos.enabled {
+                    gizmos.ray(body.position, force, Color::CYAN);
+                }
+            }
+        }
+    }
+}
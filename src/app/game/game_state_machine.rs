@@ -1,6 +1,9 @@
 use bevy::prelude::*;
 
-use crate::app::app_state_machine::AppState;
+use crate::app::{
+    app_state_machine::AppState,
+    transition_table::{InvalidTransition, TransitionTable},
+};
 
 pub struct GameStateMachinePlugin;
 
@@ -8,6 +11,7 @@ impl Plugin for GameStateMachinePlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<GameState>()
             .add_event::<GameTransitionEvent>()
+            .add_event::<InvalidTransition<GameState, GameTransitionEvent>>()
             .add_systems(
                 Update,
                 (game_transition, from_none_state).run_if(in_state(AppState::InGameLevel)),
@@ -41,9 +45,10 @@ pub enum GameState {
     Failed,
 }
 
-#[derive(Event)]
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum GameTransitionEvent {
     Play,
+    /// Not legal from any state, levels are prepared by the loading state
     Prepare,
     Pause,
     Unpause,
@@ -51,17 +56,42 @@ pub enum GameTransitionEvent {
     Fail,
 }
 
+/// Every legal transition of [`GameState`]. Getting into [`GameState::Playing`]
+/// in the first place is handled by the loading state.
+pub static GAME_TRANSITIONS: TransitionTable<GameState, GameTransitionEvent> =
+    TransitionTable::new(&[
+        (
+            GameState::Playing,
+            GameTransitionEvent::Pause,
+            GameState::Paused,
+        ),
+        (
+            GameState::Paused,
+            GameTransitionEvent::Unpause,
+            GameState::Playing,
+        ),
+        (
+            GameState::Playing,
+            GameTransitionEvent::Complete,
+            GameState::Completed,
+        ),
+        (
+            GameState::Playing,
+            GameTransitionEvent::Fail,
+            GameState::Failed,
+        ),
+    ]);
+
 // I don't want this
 fn from_none_state(
     current_state: Res<State<AppState>>,
+    game_state: Res<State<GameState>>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    if current_state.is_changed() {
-        if *current_state == AppState::InGameLevel {
-            next_state.set(GameState::AssetLoading);
-        } else {
-            next_state.set(GameState::None);
-        }
+    // Coming back from the settings opened in the pause menu we want to pick
+    // up where we left off
+    if current_state.is_changed() && *game_state == GameState::None {
+        next_state.set(GameState::AssetLoading);
     }
 }
 
@@ -70,17 +100,187 @@ fn game_transition(
     current_state: Res<State<GameState>>,
     mut next_state: ResMut<NextState<GameState>>,
     mut transition_event_reader: EventReader<GameTransitionEvent>,
+    mut invalid_transition_writer: EventWriter<InvalidTransition<GameState, GameTransitionEvent>>,
 ) {
+    let mut from = current_state.get().clone();
+
     for transition_event in transition_event_reader.iter() {
-        let next_queued = match (current_state.clone(), transition_event) {
-            (GameState::Playing, GameTransitionEvent::Pause) => GameState::Paused,
-            (GameState::Paused, GameTransitionEvent::Unpause) => GameState::Playing,
-            (GameState::Playing, GameTransitionEvent::Complete) => GameState::Completed,
-            (GameState::Playing, GameTransitionEvent::Fail) => GameState::Failed,
-            _ => panic!("Invalid state transition"),
+        let Some(next_queued) = GAME_TRANSITIONS.target(&from, transition_event) else {
+            let invalid_transition = InvalidTransition {
+                from: from.clone(),
+                kind: *transition_event,
+            };
+            warn!(
+                "Ignoring invalid game transition {:?} from {:?}",
+                invalid_transition.kind, invalid_transition.from
+            );
+            invalid_transition_writer.send(invalid_transition);
+            continue;
         };
 
-        previous_state.0 = Some(current_state.clone());
-        next_state.0 = Some(next_queued);
+        previous_state.0 = Some(from.clone());
+        next_state.set(next_queued.clone());
+        from = next_queued.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATES: [GameState; 6] = [
+        GameState::None,
+        GameState::AssetLoading,
+        GameState::Playing,
+        GameState::Paused,
+        GameState::Completed,
+        GameState::Failed,
+    ];
+
+    const KINDS: [GameTransitionEvent; 6] = [
+        GameTransitionEvent::Play,
+        GameTransitionEvent::Prepare,
+        GameTransitionEvent::Pause,
+        GameTransitionEvent::Unpause,
+        GameTransitionEvent::Complete,
+        GameTransitionEvent::Fail,
+    ];
+
+    /// Written out by hand rather than read back from [`GAME_TRANSITIONS`] so
+    /// that changing the table has to change this too.
+    static EXPECTED: [(GameState, GameTransitionEvent, GameState); 4] = [
+        (
+            GameState::Playing,
+            GameTransitionEvent::Pause,
+            GameState::Paused,
+        ),
+        (
+            GameState::Paused,
+            GameTransitionEvent::Unpause,
+            GameState::Playing,
+        ),
+        (
+            GameState::Playing,
+            GameTransitionEvent::Complete,
+            GameState::Completed,
+        ),
+        (
+            GameState::Playing,
+            GameTransitionEvent::Fail,
+            GameState::Failed,
+        ),
+    ];
+
+    fn expected_target(from: &GameState, kind: &GameTransitionEvent) -> Option<&'static GameState> {
+        EXPECTED
+            .iter()
+            .find(|(edge_from, edge_kind, _)| edge_from == from && edge_kind == kind)
+            .map(|(_, _, to)| to)
+    }
+
+    #[test]
+    fn table_matches_expected_edges() {
+        for from in &STATES {
+            for kind in &KINDS {
+                assert_eq!(
+                    GAME_TRANSITIONS.target(from, kind),
+                    expected_target(from, kind),
+                    "{:?} from {:?}",
+                    kind,
+                    from
+                );
+            }
+        }
+
+        assert_eq!(GAME_TRANSITIONS.edges().len(), EXPECTED.len());
+    }
+
+    fn app_in(state: GameState) -> App {
+        let mut app = App::new();
+        app.add_state::<GameState>()
+            .add_event::<GameTransitionEvent>()
+            .add_event::<InvalidTransition<GameState, GameTransitionEvent>>()
+            .add_systems(Update, game_transition);
+
+        app.world.insert_resource(NextState(Some(state)));
+        app.update();
+
+        app
+    }
+
+    /// Sends the event and gives `apply_state_transition` a frame to apply
+    /// the next state
+    fn transition(app: &mut App, event: GameTransitionEvent) {
+        app.world.send_event(event);
+        app.update();
+        app.update();
+    }
+
+    fn state(app: &App) -> &GameState {
+        app.world.resource::<State<GameState>>().get()
+    }
+
+    fn invalid_transitions(app: &App) -> Vec<(GameState, GameTransitionEvent)> {
+        let events = app
+            .world
+            .resource::<Events<InvalidTransition<GameState, GameTransitionEvent>>>();
+
+        events
+            .get_reader()
+            .iter(events)
+            .map(|invalid_transition| (invalid_transition.from.clone(), invalid_transition.kind))
+            .collect()
+    }
+
+    #[test]
+    fn dispatcher_takes_every_edge() {
+        for (from, kind, to) in &EXPECTED {
+            let mut app = app_in(from.clone());
+
+            transition(&mut app, *kind);
+
+            assert_eq!(state(&app), to, "{:?} from {:?}", kind, from);
+            assert!(invalid_transitions(&app).is_empty());
+        }
+    }
+
+    #[test]
+    fn a_level_only_ends_once() {
+        let mut app = app_in(GameState::Playing);
+
+        transition(&mut app, GameTransitionEvent::Fail);
+        transition(&mut app, GameTransitionEvent::Complete);
+
+        assert_eq!(state(&app), &GameState::Failed);
+        assert_eq!(
+            invalid_transitions(&app),
+            vec![(GameState::Failed, GameTransitionEvent::Complete)]
+        );
+    }
+
+    #[test]
+    fn dispatcher_rejects_illegal_transitions() {
+        let mut app = app_in(GameState::Completed);
+
+        transition(&mut app, GameTransitionEvent::Fail);
+
+        assert_eq!(state(&app), &GameState::Completed);
+        assert_eq!(
+            invalid_transitions(&app),
+            vec![(GameState::Completed, GameTransitionEvent::Fail)]
+        );
+    }
+
+    #[test]
+    fn prepare_is_rejected() {
+        let mut app = app_in(GameState::Playing);
+
+        transition(&mut app, GameTransitionEvent::Prepare);
+
+        assert_eq!(state(&app), &GameState::Playing);
+        assert_eq!(
+            invalid_transitions(&app),
+            vec![(GameState::Playing, GameTransitionEvent::Prepare)]
+        );
     }
 }
@@ -6,8 +6,13 @@ use bevy::{
     render::render_resource::{TextureViewDescriptor, TextureViewDimension},
 };
 use bevy_xpbd_3d::PhysicsSet;
+use leafwing_input_manager::prelude::ActionState;
 
-use super::game_state_machine::GameState;
+use crate::app::player_input::{action_axis, GameAction};
+
+use super::{
+    game_state_machine::GameState, gravity::GravityBound, player::Player, InGameLevelMarker,
+};
 
 pub struct GraphicsPlugin;
 
@@ -15,16 +20,25 @@ impl Plugin for GraphicsPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(AmbientLight {
             color: Color::WHITE,
-            brightness: 1.0 / 5.0f32,
+            brightness: DAY_AMBIENT_BRIGHTNESS,
         })
-        .add_systems(Update, asset_loaded.run_if(in_state(GameState::Playing)))
+        .add_systems(
+            Update,
+            (asset_loaded, day_night_cycle).run_if(in_state(GameState::Playing)),
+        )
         .add_systems(
             PostUpdate,
             (follow_behind_target, track_to_target)
                 .run_if(in_state(GameState::Playing))
                 .after(PhysicsSet::Sync),
         )
-        .add_systems(OnEnter(GameState::Playing), (setup_graphics,));
+        .add_systems(
+            OnTransition {
+                from: GameState::AssetLoading,
+                to: GameState::Playing,
+            },
+            (setup_graphics,),
+        );
     }
 }
 
@@ -32,32 +46,73 @@ impl Plugin for GraphicsPlugin {
 #[derive(Component)]
 struct MainCamera;
 
+/// How far the player has turned the camera away from behind the target
+#[derive(Component, Default)]
+struct CameraOrbit {
+    /// Around the target's up, in radians
+    yaw: f32,
+
+    /// Up over the target, in radians
+    pitch: f32,
+}
+
+/// Radians per second with the camera stick pushed all the way
+const CAMERA_TURN_SPEED: f32 = 2.0;
+
 #[derive(Component)]
 pub struct MainTrackTarget;
 
 #[derive(Component)]
 pub struct MainFollowTarget;
 
-fn setup_graphics(mut commands: Commands, asset_server: Res<AssetServer>) {
+/// The directional light lighting the level
+#[derive(Component)]
+pub struct Sun;
+
+const DAY_ILLUMINANCE: f32 = 10000.0;
+const NIGHT_ILLUMINANCE: f32 = 400.0;
+
+const DAY_AMBIENT_BRIGHTNESS: f32 = 1.0 / 5.0;
+const NIGHT_AMBIENT_BRIGHTNESS: f32 = 1.0 / 20.0;
+
+/// How far past the horizon, as the cosine of the angle to the sun, twilight
+/// lasts
+const TWILIGHT: f32 = 0.2;
+
+/// The ambient light from before the day/night cycle started changing it
+#[derive(Resource)]
+pub struct OriginalAmbientLight(AmbientLight);
+
+fn setup_graphics(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    ambient_light: Res<AmbientLight>,
+) {
+    commands.insert_resource(OriginalAmbientLight(ambient_light.clone()));
+
     // directional 'sun' light
-    commands.spawn(DirectionalLightBundle {
-        directional_light: DirectionalLight {
-            illuminance: 10000.0,
-            shadows_enabled: true,
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                illuminance: DAY_ILLUMINANCE,
+                shadows_enabled: true,
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 30.0, 0.01).looking_at(Vec3::ZERO, Vec3::Y),
+            // The default cascade config is designed to handle large scenes.
+            // As this example has a much smaller world, we can tighten the shadow
+            // bounds for better visual quality.
+            cascade_shadow_config: CascadeShadowConfigBuilder {
+                first_cascade_far_bound: 4.0,
+                maximum_distance: 10.0,
+                ..default()
+            }
+            .into(),
             ..default()
         },
-        transform: Transform::from_xyz(0.0, 30.0, 0.01).looking_at(Vec3::ZERO, Vec3::Y),
-        // The default cascade config is designed to handle large scenes.
-        // As this example has a much smaller world, we can tighten the shadow
-        // bounds for better visual quality.
-        cascade_shadow_config: CascadeShadowConfigBuilder {
-            first_cascade_far_bound: 4.0,
-            maximum_distance: 10.0,
-            ..default()
-        }
-        .into(),
-        ..default()
-    });
+        Sun,
+        InGameLevelMarker,
+    ));
 
     let camera_transform = Transform::from_xyz(0.0, 0.0, 16.0);
     let skybox_handle = asset_server.load("skyboxes/cubemap.png");
@@ -76,7 +131,9 @@ fn setup_graphics(mut commands: Commands, asset_server: Res<AssetServer>) {
         },
         BloomSettings::default(),
         MainCamera,
+        CameraOrbit::default(),
         Skybox(skybox_handle.clone()),
+        InGameLevelMarker,
     ));
 
     commands.insert_resource(Cubemap {
@@ -123,21 +180,38 @@ fn asset_loaded(
 
 /// Moves the camera to follow the target
 fn follow_behind_target(
-    target_query: Query<&Transform, (With<MainFollowTarget>, Without<MainCamera>)>,
-    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+    target_query: Query<
+        (&Transform, Option<&ActionState<GameAction>>),
+        (With<MainFollowTarget>, Without<MainCamera>),
+    >,
+    mut camera_query: Query<(&mut Transform, &mut CameraOrbit), With<MainCamera>>,
     time: Res<Time>,
 ) {
-    if let Ok(target_transform) = target_query.get_single() {
-        if let Ok(mut camera_transform) = camera_query.get_single_mut() {
+    if let Ok((target_transform, action_state)) = target_query.get_single() {
+        if let Ok((mut camera_transform, mut orbit)) = camera_query.get_single_mut() {
             // how high the camera is above the player
-            let up_offset = 2.0;
+            let up_offset: f32 = 2.0;
             // how far the camera is behind the player
-            let back_offset = 5.0;
+            let back_offset: f32 = 5.0;
+
+            let turn = action_state.map_or(Vec2::ZERO, |action_state| {
+                action_axis(action_state, GameAction::Camera)
+            }) * CAMERA_TURN_SPEED
+                * time.delta_seconds();
+            let base_pitch = up_offset.atan2(back_offset);
+
+            orbit.yaw -= turn.x;
+            // Don't go under the ground or over the top of the player
+            orbit.pitch = (orbit.pitch + turn.y).clamp(-base_pitch, 1.2);
 
             // Compute the desired camera position relative to the player
+            let distance = up_offset.hypot(back_offset);
+            let pitch = base_pitch + orbit.pitch;
+            let yaw = Quat::from_axis_angle(target_transform.up(), orbit.yaw);
             let target_camera_position = target_transform.translation
-                + target_transform.up() * up_offset
-                - target_transform.forward() * back_offset;
+                + yaw
+                    * (target_transform.up() * pitch.sin() * distance
+                        - target_transform.forward() * pitch.cos() * distance);
 
             let smooth_factor = 10.0 * time.delta_seconds();
             camera_transform.translation = camera_transform
@@ -165,3 +239,46 @@ fn track_to_target(
     // Make the camera look at the player
     camera_transform.look_at(target_transform.translation, target_transform.up());
 }
+
+/// It's day when the player's up faces the sun, so walking around a planet or
+/// standing on a spinning one makes the light come and go.
+fn day_night_cycle(
+    player_query: Query<&GravityBound, With<Player>>,
+    mut sun_query: Query<(&mut DirectionalLight, &GlobalTransform), With<Sun>>,
+    mut ambient_light: ResMut<AmbientLight>,
+) {
+    let Ok(gravity_bound) = player_query.get_single() else {
+        return;
+    };
+
+    // Keep the last lighting while floating in space
+    if gravity_bound.up == Vec3::ZERO {
+        return;
+    }
+
+    for (mut directional_light, sun_transform) in &mut sun_query {
+        let towards_sun = sun_transform.back();
+        let daylight =
+            ((gravity_bound.up.dot(towards_sun) + TWILIGHT) / (2.0 * TWILIGHT)).clamp(0.0, 1.0);
+
+        directional_light.illuminance =
+            NIGHT_ILLUMINANCE + (DAY_ILLUMINANCE - NIGHT_ILLUMINANCE) * daylight;
+        ambient_light.brightness = NIGHT_AMBIENT_BRIGHTNESS
+            + (DAY_AMBIENT_BRIGHTNESS - NIGHT_AMBIENT_BRIGHTNESS) * daylight;
+    }
+}
+
+/// Puts back the ambient light once the level is left so the menus and the
+/// next level don't stay as dark as the night the level ended in.
+pub fn restore_ambient_light(
+    mut commands: Commands,
+    original_ambient_light: Option<Res<OriginalAmbientLight>>,
+    mut ambient_light: ResMut<AmbientLight>,
+) {
+    let Some(original_ambient_light) = original_ambient_light else {
+        return;
+    };
+
+    *ambient_light = original_ambient_light.0.clone();
+    commands.remove_resource::<OriginalAmbientLight>();
+}
@@ -1,9 +1,28 @@
-use bevy::prelude::*;
-use bevy_xpbd_3d::{prelude::*, PhysicsSchedule};
+use bevy::{
+    ecs::{
+        entity::{EntityMapper, MapEntities},
+        reflect::ReflectMapEntities,
+    },
+    math::cubic_splines::{Bezier, CardinalSpline, CubicCurve, CubicGenerator},
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use bevy_xpbd_3d::{
+    parry::{
+        math::Isometry,
+        query::{PointQuery, Ray, RayCast},
+        shape::Shape,
+    },
+    prelude::*,
+    resources::DeltaTime,
+    PhysicsSchedule,
+};
+use futures_lite::future;
+use serde::Deserialize;
 
 use crate::app::game::game_state_machine::GameState;
 
-use super::DebugGizmos;
+use super::{grounded::Grounded, DebugGizmos};
 
 pub struct GravityPlugin;
 
@@ -11,15 +30,35 @@ impl Plugin for GravityPlugin {
     fn build(&self, app: &mut App) {
         use bevy_trait_query::RegisterExt;
 
-        app.register_component_as::<dyn GravitySource, PointGravity>()
+        app.register_type::<GravityBound>()
+            .register_type::<Upright>()
+            .register_type::<UprightRecovery>()
+            .register_type::<UprightAlignment>()
+            .register_type::<GravityBlending>()
+            .register_type::<BlendMode>()
+            .register_type::<GravityPriority>()
+            .register_type::<GravityDisabled>()
+            .add_event::<GravityFieldEntered>()
+            .add_event::<GravityFieldExited>()
+            .register_type::<GravityFalloff>()
+            .register_type::<FalloffKind>()
+            .register_type::<PointGravity>()
+            .register_type::<PlanarGravity>()
+            .register_type::<CurvedGravity>()
+            .register_type::<GravityCurve>()
+            .register_type::<SurfaceGravity>()
+            .register_component_as::<dyn GravitySource, PointGravity>()
             .register_component_as::<dyn GravitySource, PlanarGravity>()
+            .register_component_as::<dyn GravitySource, CurvedGravity>()
+            .register_component_as::<dyn GravitySource, SurfaceGravity>()
             .add_systems(
                 PhysicsSchedule,
                 (update_gravity, keep_upright)
                     .chain()
                     .run_if(in_state(GameState::Playing))
                     .in_set(GravitySystemSet),
-            );
+            )
+            .add_systems(Update, finish_distance_fields);
     }
 }
 
@@ -27,12 +66,58 @@ impl Plugin for GravityPlugin {
 pub struct GravitySystemSet;
 
 /// A component that indicates that an entity is affected by gravity.
-#[derive(Component, Default)]
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, MapEntities)]
 pub struct GravityBound {
-    /// The sum of all forces due to gravity acting on this entity.
+    /// The blend of all forces due to gravity acting on this entity.
     pub gravity_force: Vec3,
+
+    /// Every gravity source entity this entity is inside of
+    pub sources: Vec<Entity>,
+
+    /// The gravity source entity that wins when blending
+    pub dominant_source: Option<Entity>,
+
+    /// Points away from the dominant source, or zero outside of every source
+    pub up: Vec3,
+
+    /// The force being faded out of when blending with a crossfade
+    pub crossfade_from: Vec3,
+
+    /// How far along the crossfade is, from `0` to `1`
+    pub crossfade: f32,
 }
 
+impl MapEntities for GravityBound {
+    fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        for source in &mut self.sources {
+            *source = entity_mapper.get_or_reserve(*source);
+        }
+
+        if let Some(dominant_source) = &mut self.dominant_source {
+            *dominant_source = entity_mapper.get_or_reserve(*dominant_source);
+        }
+    }
+}
+
+/// Sent when a gravity bound entity starts feeling a gravity source.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GravityFieldEntered {
+    pub entity: Entity,
+
+    pub source: Entity,
+}
+
+/// Sent when a gravity bound entity stops feeling a gravity source.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GravityFieldExited {
+    pub entity: Entity,
+
+    pub source: Entity,
+}
+
+/// The sensor of a gravity source or force field. Bodies inside of it feel the
+/// source.
 #[derive(Bundle)]
 pub struct GravitySourceBundle {
     // TODO:
@@ -48,28 +133,142 @@ pub struct GravitySourceBundle {
 
 #[bevy_trait_query::queryable]
 pub trait GravitySource {
-    fn calculate_force(&self, position: Vec3, other_position: Vec3, mass: f32) -> Vec3;
+    /// `position` and `rotation` are those of the gravity source, so sources
+    /// with a shape can spin along with their body.
+    fn calculate_force(
+        &self,
+        position: Vec3,
+        rotation: Quat,
+        other_position: Vec3,
+        mass: f32,
+    ) -> Vec3;
+}
+
+/// How the strength of a gravity source changes between its inner and outer
+/// radius.
+#[derive(Reflect, Deserialize, Default, Debug, Clone)]
+pub enum FalloffKind {
+    /// Full strength all the way
+    #[default]
+    Constant,
+
+    /// Fades from full strength to nothing
+    Linear,
+
+    /// Newton's law, with full strength at the inner radius. Without an inner
+    /// radius there's nothing to scale from so this is constant instead
+    InverseSquare,
+
+    /// Strengths sampled evenly from the inner to the outer radius
+    Curve(Vec<f32>),
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub struct GravityFalloff {
+    pub kind: FalloffKind,
+
+    /// Bodies closer than this feel the full strength
+    pub inner_radius: f32,
+
+    /// Bodies further than this feel nothing
+    pub outer_radius: f32,
+
+    /// The distance before the outer radius over which gravity smoothly fades
+    /// out, so bodies don't get jolted at the edge of the sensor
+    pub fade: f32,
+}
+
+impl Default for GravityFalloff {
+    fn default() -> Self {
+        Self {
+            kind: FalloffKind::Constant,
+            inner_radius: 0.0,
+            outer_radius: f32::INFINITY,
+            fade: 0.0,
+        }
+    }
+}
+
+impl GravityFalloff {
+    /// Newton's law without any edge to the field
+    pub fn inverse_square(inner_radius: f32) -> Self {
+        Self {
+            kind: FalloffKind::InverseSquare,
+            inner_radius,
+            ..default()
+        }
+    }
+
+    /// How much of the full strength is felt at the given distance.
+    pub fn factor(&self, distance: f32) -> f32 {
+        if distance >= self.outer_radius {
+            return 0.0;
+        }
+
+        let factor = if distance <= self.inner_radius {
+            1.0
+        } else {
+            let t = (distance - self.inner_radius) / (self.outer_radius - self.inner_radius);
+
+            match &self.kind {
+                FalloffKind::Constant => 1.0,
+                FalloffKind::Linear => 1.0 - t,
+                FalloffKind::InverseSquare if self.inner_radius <= 0.0 => 1.0,
+                FalloffKind::InverseSquare => (self.inner_radius / distance).powi(2),
+                FalloffKind::Curve(samples) => sample_falloff_curve(samples, t),
+            }
+        };
+
+        if self.fade > 0.0 {
+            let edge = ((self.outer_radius - distance) / self.fade).clamp(0.0, 1.0);
+
+            // Smoothstep
+            factor * edge * edge * (3.0 - 2.0 * edge)
+        } else {
+            factor
+        }
+    }
+}
+
+fn sample_falloff_curve(samples: &[f32], t: f32) -> f32 {
+    match samples {
+        [] => 1.0,
+        [sample] => *sample,
+        _ => {
+            let position = t.clamp(0.0, 1.0) * (samples.len() - 1) as f32;
+            let index = (position.floor() as usize).min(samples.len() - 2);
+            let fraction = position - index as f32;
+
+            samples[index] + (samples[index + 1] - samples[index]) * fraction
+        }
+    }
 }
 
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 pub struct PointGravity {
     pub gravity_strength: f32,
 
     pub center_mass: f32,
+
+    pub falloff: GravityFalloff,
 }
 
 impl GravitySource for PointGravity {
-    fn calculate_force(&self, position: Vec3, other_position: Vec3, mass: f32) -> Vec3 {
+    fn calculate_force(
+        &self,
+        position: Vec3,
+        _rotation: Quat,
+        other_position: Vec3,
+        mass: f32,
+    ) -> Vec3 {
         // Compute distance between planet and body
         let distance = position.distance(other_position);
 
-        // Prevent division by very small numbers
-        let safe_distance = distance.max(0.001);
-
-        // Compute gravitational force as per Newton's law
         let gravity_force_magnitude =
-            self.gravity_strength * self.center_mass * mass / safe_distance.powi(2);
-        let gravity_vector = (position - other_position).normalize() * gravity_force_magnitude;
+            self.gravity_strength * self.center_mass * mass * self.falloff.factor(distance);
+        let gravity_vector =
+            (position - other_position).normalize_or_zero() * gravity_force_magnitude;
 
         gravity_vector
     }
@@ -83,146 +282,1195 @@ impl GravitySource for PointGravity {
 /// The direction of the field is perpendicular to the plane,
 /// towards it.
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 pub struct PlanarGravity {
     // The normal vector of the plane.
     pub normal: Vec3,
 
     pub gravity_strength: f32,
+
+    /// How far below the source the plane is, along the normal
+    pub plane_offset: f32,
+
+    /// Applied to the height above the plane. Constant by default, as it
+    /// would be for a real plane.
+    pub falloff: GravityFalloff,
 }
 
 impl GravitySource for PlanarGravity {
     /// Fg = m * g
-    fn calculate_force(&self, _position: Vec3, _other_position: Vec3, mass: f32) -> Vec3 {
-        let gravity_vector = -self.normal.normalize() * self.gravity_strength * mass;
+    fn calculate_force(
+        &self,
+        position: Vec3,
+        _rotation: Quat,
+        other_position: Vec3,
+        mass: f32,
+    ) -> Vec3 {
+        let normal = self.normal.normalize();
+        let height = ((other_position - position).dot(normal) + self.plane_offset).max(0.0);
+
+        let gravity_vector = -normal * self.gravity_strength * mass * self.falloff.factor(height);
 
         gravity_vector
     }
 }
 
-#[derive(Component)]
+/// The shape of a [`CurvedGravity`] field, relative to the gravity source.
+#[derive(Reflect, Deserialize, Debug, Clone)]
+pub enum GravityCurve {
+    /// Cubic Bezier segments, each given by its four control points
+    Bezier(Vec<[Vec3; 4]>),
+
+    /// A Catmull-Rom spline passing through every point
+    CatmullRom(Vec<Vec3>),
+}
+
+impl Default for GravityCurve {
+    fn default() -> Self {
+        GravityCurve::CatmullRom(Vec::new())
+    }
+}
+
+impl GravityCurve {
+    pub fn segment_count(&self) -> usize {
+        match self {
+            GravityCurve::Bezier(segments) => segments.len(),
+            GravityCurve::CatmullRom(points) => points.len().saturating_sub(1),
+        }
+    }
+
+    /// Returns `None` if there aren't enough points to make a curve.
+    pub fn to_curve(&self) -> Option<CubicCurve<Vec3>> {
+        if self.segment_count() == 0 {
+            return None;
+        }
+
+        match self {
+            GravityCurve::Bezier(segments) => Some(Bezier::new(segments.clone()).to_curve()),
+            GravityCurve::CatmullRom(points) => {
+                // A cardinal spline doesn't pass through its first and last
+                // control points so they're doubled up
+                let mut control_points = Vec::with_capacity(points.len() + 2);
+                control_points.push(points[0]);
+                control_points.extend(points.iter().copied());
+                control_points.push(points[points.len() - 1]);
+
+                Some(CardinalSpline::new_catmull_rom(control_points).to_curve())
+            }
+        }
+    }
+}
+
+/// Pulls towards the closest point on a curve, which makes for gravity
+/// "tubes" that can be walked around.
+#[derive(Component, Reflect, Default, Debug, Clone)]
+#[reflect(Component)]
 pub struct CurvedGravity {
-    // curve: Bezier
-    cubic_generator: Box<dyn CubicGenerator<Vec3> + Send + Sync>,
+    pub curve: GravityCurve,
 
-    gravity_strength: f32,
+    pub gravity_strength: f32,
+
+    /// Relative to the distance from the curve
+    pub falloff: GravityFalloff,
+}
+
+impl CurvedGravity {
+    pub fn new(curve: GravityCurve, gravity_strength: f32) -> Self {
+        Self {
+            curve,
+            gravity_strength,
+            falloff: GravityFalloff::default(),
+        }
+    }
+
+    pub fn with_falloff(mut self, falloff: GravityFalloff) -> Self {
+        self.falloff = falloff;
+        self
+    }
+
+    /// The point on the curve closest to `point`, both relative to the
+    /// gravity source.
+    pub fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        let curve = self.curve.to_curve()?;
+
+        Some(closest_point_on_curve(
+            &curve,
+            self.curve.segment_count(),
+            point,
+        ))
+    }
 }
 
 impl GravitySource for CurvedGravity {
-    fn calculate_force(&self, position: Vec3, _other_position: Vec3, mass: f32) -> Vec3 {
-        const MAX_ITER: usize = 10;
-        const EPSILON: f32 = 1e-6;
-        let curve = self.cubic_generator.to_curve();
-
-        // Newton's method to find the closest point on the curve.
-        let mut t = 0.5; // starting with the middle of the curve
-        for _ in 0..MAX_ITER {
-            let pos = curve.position(t);
-            let vel = curve.velocity(t);
-            let acc = curve.acceleration(t);
-
-            let diff = pos - position;
-            let diff_norm = diff.length();
-            if diff_norm < EPSILON {
-                // We're close enough
-                break;
+    fn calculate_force(
+        &self,
+        position: Vec3,
+        rotation: Quat,
+        other_position: Vec3,
+        mass: f32,
+    ) -> Vec3 {
+        let local_position = rotation.inverse() * (other_position - position);
+
+        let Some(closest_point) = self.closest_point(local_position) else {
+            return Vec3::ZERO;
+        };
+
+        let offset = rotation * (closest_point - local_position);
+
+        offset.normalize_or_zero()
+            * self.gravity_strength
+            * mass
+            * self.falloff.factor(offset.length())
+    }
+}
+
+/// Newton's method only finds the closest point from a good initial guess, so
+/// the curve is sampled first, ends included, and the best sample is refined
+/// without leaving its neighbourhood.
+fn closest_point_on_curve(curve: &CubicCurve<Vec3>, segments: usize, point: Vec3) -> Vec3 {
+    const SAMPLES_PER_SEGMENT: usize = 8;
+    const MAX_ITER: usize = 8;
+    const EPSILON: f32 = 1e-6;
+
+    let distance_squared = |t: f32| curve.position(t).distance_squared(point);
+    let step = 1.0 / SAMPLES_PER_SEGMENT as f32;
+
+    let (best_t, best_distance) = (0..=segments * SAMPLES_PER_SEGMENT)
+        .map(|sample| {
+            let t = sample as f32 * step;
+            (t, distance_squared(t))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap_or((0.0, distance_squared(0.0)));
+
+    let min_t = (best_t - step).max(0.0);
+    let max_t = (best_t + step).min(segments as f32);
+
+    // Minimize the squared distance by finding where its derivative is zero
+    let mut t = best_t;
+    for _ in 0..MAX_ITER {
+        let offset = curve.position(t) - point;
+        let velocity = curve.velocity(t);
+
+        let slope = offset.dot(velocity);
+        let curvature = velocity.length_squared() + offset.dot(curve.acceleration(t));
+
+        // Not near a minimum so we stick with the sample
+        if curvature <= EPSILON {
+            break;
+        }
+
+        let t_next = (t - slope / curvature).clamp(min_t, max_t);
+        let converged = (t_next - t).abs() < EPSILON;
+        t = t_next;
+
+        if converged {
+            break;
+        }
+    }
+
+    if distance_squared(t) < best_distance {
+        curve.position(t)
+    } else {
+        curve.position(best_t)
+    }
+}
+
+/// Pulls towards the closest point on the surface of a shape, so bodies stay
+/// on flat faces, lobes and holes of bodies that aren't spheres.
+#[derive(Component, Reflect)]
+#[reflect(Component, from_reflect = false)]
+pub struct SurfaceGravity {
+    /// The shape of the body, relative to the gravity source
+    #[reflect(ignore)]
+    pub shape: Collider,
+
+    /// Speeds up finding the surface, at the cost of precision
+    #[reflect(ignore)]
+    pub distance_field: Option<SignedDistanceField>,
+
+    pub gravity_strength: f32,
+
+    /// Relative to the distance from the surface
+    pub falloff: GravityFalloff,
+}
+
+impl Default for SurfaceGravity {
+    fn default() -> Self {
+        Self::new(Collider::ball(1.0), 0.0)
+    }
+}
+
+impl SurfaceGravity {
+    pub fn new(shape: Collider, gravity_strength: f32) -> Self {
+        Self {
+            shape,
+            distance_field: None,
+            gravity_strength,
+            falloff: GravityFalloff::default(),
+        }
+    }
+
+    pub fn with_falloff(mut self, falloff: GravityFalloff) -> Self {
+        self.falloff = falloff;
+        self
+    }
+
+    /// Starts baking a distance field with cells of the given size covering
+    /// `half_extent` around the gravity source. Until the task is done the
+    /// surface is found without it.
+    pub fn bake_distance_field(&self, half_extent: f32, cell_size: f32) -> DistanceFieldTask {
+        let shape = self.shape.clone();
+
+        DistanceFieldTask(AsyncComputeTaskPool::get().spawn(async move {
+            SignedDistanceField::from_collider(&shape, half_extent, cell_size)
+        }))
+    }
+
+    /// The direction towards the surface from `point`, relative to the gravity
+    /// source, and how far away the surface is.
+    pub fn towards_surface(&self, point: Vec3) -> (Vec3, f32) {
+        if let Some((gradient, distance)) =
+            self.distance_field.as_ref().and_then(|distance_field| {
+                Some((
+                    distance_field.gradient(point)?,
+                    distance_field.distance(point)?,
+                ))
+            })
+        {
+            return (-gradient.normalize_or_zero(), distance.abs());
+        }
+
+        let shape = self.shape.get_shape();
+        let projection = shape.project_point(&Isometry::identity(), &point.into(), false);
+        let to_surface = Vec3::from(projection.point) - point;
+
+        // From the inside the surface is "up"
+        if is_inside(&**shape, point) {
+            (-to_surface.normalize_or_zero(), to_surface.length())
+        } else {
+            (to_surface.normalize_or_zero(), to_surface.length())
+        }
+    }
+}
+
+impl GravitySource for SurfaceGravity {
+    fn calculate_force(
+        &self,
+        position: Vec3,
+        rotation: Quat,
+        other_position: Vec3,
+        mass: f32,
+    ) -> Vec3 {
+        let (down, distance) =
+            self.towards_surface(rotation.inverse() * (other_position - position));
+
+        rotation * down * self.gravity_strength * mass * self.falloff.factor(distance)
+    }
+}
+
+/// Baking a [`SignedDistanceField`] for the [`SurfaceGravity`] on the same
+/// entity, see [`SurfaceGravity::bake_distance_field`].
+#[derive(Component)]
+pub struct DistanceFieldTask(Task<SignedDistanceField>);
+
+fn finish_distance_fields(
+    mut commands: Commands,
+    mut surface_query: Query<(Entity, &mut SurfaceGravity, &mut DistanceFieldTask)>,
+) {
+    for (entity, mut surface_gravity, mut task) in &mut surface_query {
+        let Some(distance_field) = future::block_on(future::poll_once(&mut task.0)) else {
+            continue;
+        };
+
+        surface_gravity.distance_field = Some(distance_field);
+        commands.entity(entity).remove::<DistanceFieldTask>();
+    }
+}
+
+/// Whether `point` is inside a closed shape, by counting how often rays leaving
+/// it cross the surface. Projecting onto a triangle mesh can't tell which side
+/// of it is the inside.
+fn is_inside(shape: &dyn Shape, point: Vec3) -> bool {
+    // Skewed so they're unlikely to graze an edge, and a majority vote in case
+    // one does anyway
+    const DIRECTIONS: [Vec3; 3] = [
+        Vec3::new(0.577, 0.579, 0.576),
+        Vec3::new(-0.802, 0.267, 0.534),
+        Vec3::new(0.229, -0.688, 0.689),
+    ];
+
+    DIRECTIONS
+        .iter()
+        .filter(|&&direction| surface_crossings(shape, point, direction) % 2 == 1)
+        .count()
+        >= 2
+}
+
+fn surface_crossings(shape: &dyn Shape, point: Vec3, direction: Vec3) -> usize {
+    const MAX_CROSSINGS: usize = 64;
+    const EPSILON: f32 = 1e-3;
+
+    let mut origin = point;
+    let mut crossings = 0;
+
+    while crossings < MAX_CROSSINGS {
+        let ray = Ray::new(origin.into(), direction.into());
+        let Some(time_of_impact) = shape.cast_local_ray(&ray, f32::MAX, false) else {
+            break;
+        };
+
+        crossings += 1;
+        origin += direction * (time_of_impact + EPSILON);
+    }
+
+    crossings
+}
+
+/// Distances to the surface of a shape sampled on a regular grid. Distances are
+/// negative inside the shape.
+#[derive(Debug, Clone)]
+pub struct SignedDistanceField {
+    /// The corner of the grid with the smallest coordinates
+    pub origin: Vec3,
+
+    pub cell_size: f32,
+
+    /// The number of samples along each axis
+    pub samples: UVec3,
+
+    pub distances: Vec<f32>,
+}
+
+impl SignedDistanceField {
+    pub fn from_collider(collider: &Collider, half_extent: f32, cell_size: f32) -> Self {
+        let origin = Vec3::splat(-half_extent);
+        let samples = UVec3::splat((2.0 * half_extent / cell_size).ceil() as u32 + 1);
+        let shape = collider.get_shape();
+
+        let mut distances = Vec::with_capacity((samples.x * samples.y * samples.z) as usize);
+        for z in 0..samples.z {
+            for y in 0..samples.y {
+                for x in 0..samples.x {
+                    let point = origin + UVec3::new(x, y, z).as_vec3() * cell_size;
+                    let projection =
+                        shape.project_point(&Isometry::identity(), &point.into(), false);
+                    let distance = point.distance(Vec3::from(projection.point));
+
+                    distances.push(if is_inside(&**shape, point) {
+                        -distance
+                    } else {
+                        distance
+                    });
+                }
             }
+        }
 
-            let proj_vel = diff.dot(vel);
-            let proj_acc = diff.dot(acc);
+        Self {
+            origin,
+            cell_size,
+            samples,
+            distances,
+        }
+    }
+
+    /// The trilinearly interpolated distance at `point`, or `None` outside the
+    /// grid.
+    pub fn distance(&self, point: Vec3) -> Option<f32> {
+        let grid_point = (point - self.origin) / self.cell_size;
+        let max = (self.samples - UVec3::ONE).as_vec3();
+
+        if grid_point.cmplt(Vec3::ZERO).any() || grid_point.cmpgt(max).any() {
+            return None;
+        }
+
+        let cell = grid_point.floor().min(max - Vec3::ONE).max(Vec3::ZERO);
+        let fraction = grid_point - cell;
+        let cell = cell.as_uvec3();
+
+        let sample = |offset: UVec3| {
+            let index = cell + offset;
+            self.distances
+                [(index.x + self.samples.x * (index.y + self.samples.y * index.z)) as usize]
+        };
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let along_x = |y: u32, z: u32| {
+            lerp(
+                sample(UVec3::new(0, y, z)),
+                sample(UVec3::new(1, y, z)),
+                fraction.x,
+            )
+        };
+
+        Some(lerp(
+            lerp(along_x(0, 0), along_x(1, 0), fraction.y),
+            lerp(along_x(0, 1), along_x(1, 1), fraction.y),
+            fraction.z,
+        ))
+    }
+
+    /// Points away from the surface, or `None` outside the grid.
+    pub fn gradient(&self, point: Vec3) -> Option<Vec3> {
+        let h = self.cell_size * 0.5;
+        let difference =
+            |axis: Vec3| Some(self.distance(point + axis * h)? - self.distance(point - axis * h)?);
+
+        Some(Vec3::new(
+            difference(Vec3::X)?,
+            difference(Vec3::Y)?,
+            difference(Vec3::Z)?,
+        ))
+    }
+}
+
+/// How a body combines the gravity of every source it's inside of.
+#[derive(Reflect, Default, Debug, Clone, Copy, PartialEq)]
+pub enum BlendMode {
+    /// Every source adds up
+    #[default]
+    Sum,
+
+    /// Only the dominant source pulls
+    Strongest,
+
+    /// Only sources in the dominant source's [`GravityPriority`] layer pull
+    Priority,
+
+    /// Only the dominant source pulls, fading over from the previous one
+    Crossfade { seconds: f32 },
+}
+
+/// Bodies without this sum up every source they're inside of.
+#[derive(Component, Reflect, Default, Debug, Clone)]
+#[reflect(Component)]
+pub struct GravityBlending {
+    pub mode: BlendMode,
+
+    /// How much stronger, relatively, another source has to be before it takes
+    /// over from the dominant source. Stops flickering between sources at
+    /// their boundaries.
+    pub hysteresis: f32,
+}
+
+impl GravityBlending {
+    pub fn new(mode: BlendMode) -> Self {
+        Self {
+            mode,
+            hysteresis: 0.0,
+        }
+    }
+
+    pub fn with_hysteresis(mut self, hysteresis: f32) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+}
+
+/// Sources in a higher layer win over sources in lower ones when blending with
+/// [`BlendMode::Priority`]. Sources without this are in layer `0`.
+#[derive(Component, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[reflect(Component)]
+pub struct GravityPriority(pub i32);
+
+/// Turns a gravity source off without despawning it, e.g. by a switch.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+pub struct GravityDisabled;
 
-            let t_next = t - proj_vel / (proj_acc - proj_vel.powi(2) / diff_norm);
+/// The combined force of every gravity source on a single entity.
+pub struct Contribution {
+    pub source: Entity,
 
-            // Make sure t stays within [0, 1]
-            t = t_next.max(0.0).min(1.0);
+    pub force: Vec3,
+
+    pub priority: i32,
+}
+
+/// The source that blending is built around. `current` is the source that was
+/// dominant before, which keeps it for as long as the blending's hysteresis
+/// allows.
+pub fn dominant_contribution<'a>(
+    contributions: &'a [Contribution],
+    current: Option<Entity>,
+    blending: &GravityBlending,
+) -> Option<&'a Contribution> {
+    let use_priority = blending.mode == BlendMode::Priority;
+    let key = |contribution: &Contribution| {
+        (
+            if use_priority {
+                contribution.priority
+            } else {
+                0
+            },
+            contribution.force.length(),
+        )
+    };
+
+    let strongest = contributions.iter().max_by(|a, b| {
+        key(a)
+            .partial_cmp(&key(b))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    })?;
+
+    // The current source stays dominant until it's clearly beaten
+    if let Some(current) = contributions
+        .iter()
+        .find(|contribution| Some(contribution.source) == current)
+    {
+        let (current_priority, current_strength) = key(current);
+        let (strongest_priority, strongest_strength) = key(strongest);
+
+        if current_priority >= strongest_priority
+            && strongest_strength <= current_strength * (1.0 + blending.hysteresis)
+        {
+            return Some(current);
         }
+    }
+
+    Some(strongest)
+}
 
-        let closest_point = curve.position(t);
-        let direction = (closest_point - position).normalize();
+/// The blended force for every mode but [`BlendMode::Crossfade`], which has to
+/// remember what it's fading from. Crossfades are treated as the strongest
+/// source here.
+pub fn blended_force(
+    contributions: &[Contribution],
+    dominant: Option<&Contribution>,
+    mode: BlendMode,
+) -> Vec3 {
+    match mode {
+        BlendMode::Sum => contributions
+            .iter()
+            .map(|contribution| contribution.force)
+            .sum(),
+        BlendMode::Strongest | BlendMode::Crossfade { .. } => {
+            dominant.map_or(Vec3::ZERO, |contribution| contribution.force)
+        }
+        BlendMode::Priority => {
+            let layer = dominant.map(|contribution| contribution.priority);
 
-        direction * self.gravity_strength * mass
+            contributions
+                .iter()
+                .filter(|contribution| Some(contribution.priority) == layer)
+                .map(|contribution| contribution.force)
+                .sum()
+        }
     }
 }
 
 // This function gets all rigid bodies currently in a collision with a sensor. If that sensor is
 // has a GravitySource component it then calculates the force due to that gravity source and
-// applies it to the rigid body.
+// blends it with the others according to the body's GravityBlending.
 fn update_gravity(
     debug_gizmos: Res<DebugGizmos>,
+    delta_time: Res<DeltaTime>,
     mut gizmos: Gizmos,
+    mut field_entered_writer: EventWriter<GravityFieldEntered>,
+    mut field_exited_writer: EventWriter<GravityFieldExited>,
     mut rigid_body_query: Query<
         (
+            Entity,
             RigidBodyQuery,
             &mut ExternalForce,
             &CollidingEntities,
             &mut GravityBound,
+            Option<&GravityBlending>,
         ),
         Without<Sensor>,
     >,
-    gravity_source_query: Query<(&dyn GravitySource, &Position), With<Sensor>>,
+    gravity_source_query: Query<
+        (
+            &dyn GravitySource,
+            &Position,
+            &Rotation,
+            Option<&GravityPriority>,
+        ),
+        (With<Sensor>, Without<GravityDisabled>),
+    >,
 ) {
-    for (rb_item, mut external_force, colliding_entities, mut gravity_bound) in
+    let default_blending = GravityBlending::default();
+
+    for (entity, rb_item, mut external_force, colliding_entities, mut gravity_bound, blending) in
         rigid_body_query.iter_mut()
     {
         if !rb_item.rb.is_dynamic() {
             continue;
         }
 
+        let blending = blending.unwrap_or(&default_blending);
+
+        let mut contributions = Vec::new();
         for colliding_entity in colliding_entities.0.iter() {
-            if let Ok((gravity_sources, position)) = gravity_source_query.get(*colliding_entity) {
+            if let Ok((gravity_sources, position, rotation, priority)) =
+                gravity_source_query.get(*colliding_entity)
+            {
+                let mut force = Vec3::ZERO;
+
                 for gravity_source in gravity_sources {
-                    let gravity_force = gravity_source.calculate_force(
+                    force += gravity_source.calculate_force(
                         position.0,
+                        rotation.0,
                         rb_item.position.0,
                         rb_item.mass.0,
                     );
+                }
 
-                    external_force.apply_force(gravity_force);
+                if debug_gizmos.enabled {
+                    gizmos.ray(position.0, position.0 - force, Color::BLUE);
+                }
+
+                contributions.push(Contribution {
+                    source: *colliding_entity,
+                    force,
+                    priority: priority.copied().unwrap_or_default().0,
+                });
+            }
+        }
+
+        let dominant =
+            dominant_contribution(&contributions, gravity_bound.dominant_source, blending);
+        let dominant_source = dominant.map(|contribution| contribution.source);
+        let dominant_force = dominant.map_or(Vec3::ZERO, |contribution| contribution.force);
 
-                    if debug_gizmos.enabled {
-                        gizmos.ray(position.0, position.0 - gravity_force, Color::BLUE);
-                    }
+        let gravity_force = match blending.mode {
+            BlendMode::Crossfade { seconds } => {
+                if dominant_source != gravity_bound.dominant_source {
+                    gravity_bound.crossfade_from = gravity_bound.gravity_force;
+                    gravity_bound.crossfade = 0.0;
                 }
+
+                gravity_bound.crossfade = if seconds > 0.0 {
+                    (gravity_bound.crossfade + delta_time.0 / seconds).min(1.0)
+                } else {
+                    1.0
+                };
+
+                // Smoothstep
+                let t = gravity_bound.crossfade;
+                gravity_bound
+                    .crossfade_from
+                    .lerp(dominant_force, t * t * (3.0 - 2.0 * t))
             }
+            mode => blended_force(&contributions, dominant, mode),
+        };
+
+        external_force.apply_force(gravity_force);
+
+        let sources: Vec<Entity> = contributions
+            .iter()
+            .map(|contribution| contribution.source)
+            .collect();
+
+        for &source in sources
+            .iter()
+            .filter(|source| !gravity_bound.sources.contains(source))
+        {
+            field_entered_writer.send(GravityFieldEntered { entity, source });
         }
 
-        gravity_bound.gravity_force = external_force.force();
+        for &source in gravity_bound
+            .sources
+            .iter()
+            .filter(|source| !sources.contains(source))
+        {
+            field_exited_writer.send(GravityFieldExited { entity, source });
+        }
+
+        gravity_bound.gravity_force = gravity_force;
+        gravity_bound.sources = sources;
+        gravity_bound.dominant_source = dominant_source;
+        gravity_bound.up = -dominant_force.normalize_or_zero();
     }
 }
 
-#[derive(Component)]
-pub struct Upright;
+/// Turns a body so its up axis follows gravity, or the ground it stands on.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct Upright {
+    /// How quickly the body turns upright. About this many times the remaining
+    /// angle is closed each second, no matter the frame rate.
+    pub speed: f32,
+
+    /// Angle in degrees past which the body counts as knocked over
+    pub threshold: f32,
+
+    pub recovery: UprightRecovery,
+
+    pub align_to: UprightAlignment,
+
+    /// Seconds the body has been knocked over for
+    pub knocked_over: f32,
+}
+
+impl Default for Upright {
+    fn default() -> Self {
+        Self {
+            speed: 20.0,
+            threshold: 60.0,
+            recovery: UprightRecovery::Never,
+            align_to: UprightAlignment::Gravity,
+            knocked_over: 0.0,
+        }
+    }
+}
+
+/// What happens once a body is tilted past its [`Upright::threshold`].
+#[derive(Reflect, Default, Debug, Clone, Copy, PartialEq)]
+pub enum UprightRecovery {
+    /// Stay down, e.g. junk that's been tipped over
+    #[default]
+    Never,
+
+    /// Get back up after a while, at its own speed
+    After { seconds: f32, speed: f32 },
+}
 
-const ROTATION_SPEED: f32 = 0.3; // Adjust this value for faster/slower rotation correction
+#[derive(Reflect, Default, Debug, Clone, Copy, PartialEq)]
+pub enum UprightAlignment {
+    #[default]
+    Gravity,
 
-const ROTATION_THRESHOLD: f32 = 60.0; // Angle in degrees. Adjust based on desired gameplay.
+    /// The normal of the ground the body is [`Grounded`] on, or gravity while
+    /// it's in the air
+    Ground,
+}
 
-pub fn keep_upright(mut gravity_bound_query: Query<(&mut Rotation, &GravityBound), With<Upright>>) {
-    for (mut rotation, gravity_bound) in &mut gravity_bound_query {
+pub fn keep_upright(
+    delta_time: Res<DeltaTime>,
+    mut upright_query: Query<(
+        &mut Rotation,
+        &mut Upright,
+        &GravityBound,
+        Option<&Grounded>,
+    )>,
+) {
+    for (mut rotation, mut upright, gravity_bound, grounded) in &mut upright_query {
         let gravity_force = gravity_bound.gravity_force;
         if gravity_force == Vec3::ZERO {
             continue;
         }
 
         let gravity_up = -gravity_force.normalize();
-        let player_up = rotation.0 * Vec3::Y;
+        let ground_up = grounded
+            .filter(|_| upright.align_to == UprightAlignment::Ground)
+            .map(|grounded| grounded.normal)
+            .filter(|normal| *normal != Vec3::ZERO);
 
-        // Calculate the rotation from the player's up vector to the gravity up vector
-        let rotation_angle = player_up.angle_between(gravity_up).to_degrees();
+        let target_up = ground_up.unwrap_or(gravity_up);
+        let body_up = rotation.0 * Vec3::Y;
+        let angle = body_up.angle_between(target_up).to_degrees();
 
-        // If the angle exceeds the threshold, don't try to correct
-        if rotation_angle > ROTATION_THRESHOLD {
-            continue;
+        let speed = if angle > upright.threshold {
+            upright.knocked_over += delta_time.0;
+
+            match upright.recovery {
+                UprightRecovery::After { seconds, speed } if upright.knocked_over >= seconds => {
+                    speed
+                }
+                _ => continue,
+            }
+        } else {
+            upright.knocked_over = 0.0;
+            upright.speed
+        };
+
+        let target_rotation = Quat::from_rotation_arc(body_up, target_up) * rotation.0;
+
+        // Exponential smoothing so the body turns just as fast at any frame rate
+        let t = 1.0 - (-speed * delta_time.0).exp();
+        rotation.0 = rotation.0.slerp(target_rotation, t).normalize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    const STRENGTH: f32 = 10.0;
+
+    /// Control points for a straight Bezier segment, spaced evenly so it's
+    /// traversed at a constant speed
+    fn straight(from: Vec3, to: Vec3) -> [Vec3; 4] {
+        [from, from.lerp(to, 1.0 / 3.0), from.lerp(to, 2.0 / 3.0), to]
+    }
+
+    fn force(gravity: &CurvedGravity, point: Vec3) -> Vec3 {
+        gravity.calculate_force(Vec3::ZERO, Quat::IDENTITY, point, 1.0)
+    }
+
+    fn assert_pulls_along(force: Vec3, direction: Vec3) {
+        assert!(force.is_finite(), "{:?} isn't finite", force);
+        assert!(
+            force
+                .normalize_or_zero()
+                .abs_diff_eq(direction.normalize(), 1e-3),
+            "{:?} doesn't point along {:?}",
+            force,
+            direction
+        );
+        assert!((force.length() - STRENGTH).abs() < 1e-3, "{:?}", force);
+    }
+
+    #[test]
+    fn pulls_towards_curve_endpoints() {
+        let gravity = CurvedGravity::new(
+            GravityCurve::Bezier(vec![straight(Vec3::ZERO, Vec3::X * 4.0)]),
+            STRENGTH,
+        );
+
+        // Before the start, past the end and right above both ends
+        assert_pulls_along(
+            force(&gravity, Vec3::new(-2.0, 0.0, 1.0)),
+            Vec3::new(2.0, 0.0, -1.0),
+        );
+        assert_pulls_along(
+            force(&gravity, Vec3::new(6.0, 1.0, 0.0)),
+            Vec3::new(-2.0, -1.0, 0.0),
+        );
+        assert_pulls_along(force(&gravity, Vec3::Y * 2.0), Vec3::NEG_Y);
+        assert_pulls_along(force(&gravity, Vec3::new(4.0, 0.0, 2.0)), Vec3::NEG_Z);
+    }
+
+    #[test]
+    fn pulls_towards_bezier_joins() {
+        // An L shape bending at (4, 0, 0)
+        let gravity = CurvedGravity::new(
+            GravityCurve::Bezier(vec![
+                straight(Vec3::ZERO, Vec3::X * 4.0),
+                straight(Vec3::X * 4.0, Vec3::new(4.0, 0.0, 4.0)),
+            ]),
+            STRENGTH,
+        );
+
+        assert_pulls_along(force(&gravity, Vec3::new(4.0, 2.0, 0.0)), Vec3::NEG_Y);
+        // Outside the corner only the join itself is closest
+        assert_pulls_along(
+            force(&gravity, Vec3::new(5.0, 2.0, -1.0)),
+            Vec3::new(-1.0, -2.0, 1.0),
+        );
+        // Either side of the join
+        assert_pulls_along(
+            force(&gravity, Vec3::new(3.5, 1.0, -1.0)),
+            Vec3::new(0.0, -1.0, 1.0),
+        );
+        assert_pulls_along(
+            force(&gravity, Vec3::new(5.0, 1.0, 0.5)),
+            Vec3::new(-1.0, -1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn pulls_towards_catmull_rom_joins() {
+        let gravity = CurvedGravity::new(
+            GravityCurve::CatmullRom(vec![Vec3::ZERO, Vec3::X * 4.0, Vec3::new(4.0, 0.0, 4.0)]),
+            STRENGTH,
+        );
+
+        // The curve is flat so anything straight above a point it passes
+        // through is pulled straight down
+        assert_pulls_along(force(&gravity, Vec3::new(4.0, 3.0, 0.0)), Vec3::NEG_Y);
+        assert_pulls_along(force(&gravity, Vec3::Y * 3.0), Vec3::NEG_Y);
+        assert_pulls_along(force(&gravity, Vec3::new(4.0, 3.0, 4.0)), Vec3::NEG_Y);
+
+        // Past either end
+        assert_pulls_along(force(&gravity, Vec3::X * -2.0), Vec3::X);
+        assert_pulls_along(force(&gravity, Vec3::new(4.0, 0.0, 6.0)), Vec3::NEG_Z);
+    }
+
+    #[test]
+    fn points_on_the_curve_dont_produce_nan() {
+        let bezier = CurvedGravity::new(
+            GravityCurve::Bezier(vec![
+                straight(Vec3::ZERO, Vec3::X * 4.0),
+                straight(Vec3::X * 4.0, Vec3::new(4.0, 0.0, 4.0)),
+            ]),
+            STRENGTH,
+        );
+        let catmull_rom = CurvedGravity::new(
+            GravityCurve::CatmullRom(vec![Vec3::ZERO, Vec3::X * 4.0, Vec3::new(4.0, 0.0, 4.0)]),
+            STRENGTH,
+        );
+
+        for point in [
+            Vec3::ZERO,
+            Vec3::X * 2.0,
+            Vec3::X * 4.0,
+            Vec3::new(4.0, 0.0, 4.0),
+        ] {
+            assert!(force(&bezier, point).is_finite(), "{:?}", point);
+            assert!(force(&catmull_rom, point).is_finite(), "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn follows_a_rotated_source() {
+        let gravity = CurvedGravity::new(
+            GravityCurve::Bezier(vec![straight(Vec3::ZERO, Vec3::X * 4.0)]),
+            STRENGTH,
+        );
+
+        // The curve now runs from (10, 0, 0) to (10, 4, 0)
+        let position = Vec3::X * 10.0;
+        let rotation = Quat::from_rotation_z(FRAC_PI_2);
+        let force = |point: Vec3| gravity.calculate_force(position, rotation, point, 1.0);
+
+        assert_pulls_along(force(Vec3::new(12.0, 2.0, 0.0)), Vec3::NEG_X);
+        assert_pulls_along(force(Vec3::new(10.0, 6.0, 0.0)), Vec3::NEG_Y);
+        assert_pulls_along(force(Vec3::new(10.0, -1.0, 1.0)), Vec3::new(0.0, 1.0, -1.0));
+    }
+
+    /// A closed cube from -1 to 1 along each axis, as a triangle mesh
+    fn cube_mesh() -> Collider {
+        let vertices = (0..8)
+            .map(|corner| {
+                Vec3::new(
+                    if corner & 1 == 0 { -1.0 } else { 1.0 },
+                    if corner & 2 == 0 { -1.0 } else { 1.0 },
+                    if corner & 4 == 0 { -1.0 } else { 1.0 },
+                )
+            })
+            .collect();
+        let indices = vec![
+            [0, 2, 1],
+            [1, 2, 3],
+            [4, 5, 6],
+            [5, 7, 6],
+            [0, 1, 4],
+            [1, 5, 4],
+            [2, 6, 3],
+            [3, 6, 7],
+            [0, 4, 2],
+            [2, 4, 6],
+            [1, 3, 5],
+            [3, 7, 5],
+        ];
+
+        Collider::trimesh(vertices, indices)
+    }
+
+    #[test]
+    fn tells_the_inside_of_a_mesh_apart() {
+        let cube = cube_mesh();
+        let shape = &**cube.get_shape();
+
+        assert!(is_inside(shape, Vec3::ZERO));
+        assert!(is_inside(shape, Vec3::new(0.9, -0.5, 0.2)));
+        assert!(!is_inside(shape, Vec3::new(0.0, 2.0, 0.0)));
+        assert!(!is_inside(shape, Vec3::new(-3.0, 0.5, 1.5)));
+    }
+
+    #[test]
+    fn distance_fields_are_negative_inside() {
+        let distance_field = SignedDistanceField::from_collider(&cube_mesh(), 2.0, 0.5);
+
+        assert!(distance_field.distance(Vec3::ZERO).unwrap() < 0.0);
+        assert!(distance_field.distance(Vec3::new(0.0, 1.5, 0.0)).unwrap() > 0.0);
+
+        // Points away from the surface, outwards on the outside
+        let gradient = distance_field.gradient(Vec3::new(0.0, 1.5, 0.0)).unwrap();
+        assert!(
+            gradient.normalize().abs_diff_eq(Vec3::Y, 1e-3),
+            "{:?}",
+            gradient
+        );
+    }
+
+    #[test]
+    fn curves_need_a_segment() {
+        let gravity = CurvedGravity::new(GravityCurve::CatmullRom(vec![Vec3::ZERO]), STRENGTH);
+
+        assert_eq!(force(&gravity, Vec3::Y), Vec3::ZERO);
+    }
+
+    #[test]
+    fn inverse_square_without_inner_radius_is_constant() {
+        let falloff = GravityFalloff {
+            outer_radius: 10.0,
+            ..GravityFalloff::inverse_square(0.0)
+        };
+
+        assert_eq!(falloff.factor(0.0), 1.0);
+        assert_eq!(falloff.factor(5.0), 1.0);
+        assert_eq!(falloff.factor(10.0), 0.0);
+
+        let falloff = GravityFalloff::inverse_square(2.0);
+
+        assert_eq!(falloff.factor(1.0), 1.0);
+        assert!((falloff.factor(4.0) - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn planar_falloff_follows_height_above_plane() {
+        // The source sits 2 above a plane through the origin, fading linearly
+        // over the 4 above it
+        let gravity = PlanarGravity {
+            normal: Vec3::Y,
+            gravity_strength: STRENGTH,
+            plane_offset: 2.0,
+            falloff: GravityFalloff {
+                kind: FalloffKind::Linear,
+                outer_radius: 4.0,
+                ..default()
+            },
+        };
+        let force = |height: f32| {
+            gravity.calculate_force(Vec3::Y * 2.0, Quat::IDENTITY, Vec3::Y * height, 1.0)
+        };
+
+        assert!(force(0.0).abs_diff_eq(Vec3::NEG_Y * STRENGTH, 1e-4));
+        assert!(force(1.0).abs_diff_eq(Vec3::NEG_Y * STRENGTH * 0.75, 1e-4));
+        assert!(force(3.0).abs_diff_eq(Vec3::NEG_Y * STRENGTH * 0.25, 1e-4));
+        assert_eq!(force(5.0), Vec3::ZERO);
+
+        // Without a falloff it's the same at any height
+        let gravity = PlanarGravity {
+            normal: Vec3::Y,
+            gravity_strength: STRENGTH,
+            ..default()
+        };
+
+        assert_eq!(
+            gravity.calculate_force(Vec3::ZERO, Quat::IDENTITY, Vec3::Y * 100.0, 1.0),
+            Vec3::NEG_Y * STRENGTH
+        );
+    }
+
+    fn contribution(source: u32, force: Vec3, priority: i32) -> Contribution {
+        Contribution {
+            source: Entity::from_raw(source),
+            force,
+            priority,
+        }
+    }
+
+    /// A weak source in a high layer and two stronger ones in the layer below
+    fn layered_contributions() -> Vec<Contribution> {
+        vec![
+            contribution(0, Vec3::NEG_Y * 2.0, 1),
+            contribution(1, Vec3::NEG_X * 5.0, 0),
+            contribution(2, Vec3::NEG_Z * 3.0, 0),
+        ]
+    }
+
+    fn blend(contributions: &[Contribution], mode: BlendMode) -> Vec3 {
+        let dominant = dominant_contribution(contributions, None, &GravityBlending::new(mode));
+
+        blended_force(contributions, dominant, mode)
+    }
+
+    #[test]
+    fn sum_adds_up_every_source() {
+        assert_eq!(
+            blend(&layered_contributions(), BlendMode::Sum),
+            Vec3::new(-5.0, -2.0, -3.0)
+        );
+    }
+
+    #[test]
+    fn strongest_only_pulls_with_the_strongest_source() {
+        assert_eq!(
+            blend(&layered_contributions(), BlendMode::Strongest),
+            Vec3::NEG_X * 5.0
+        );
+        // Without memory crossfades have nothing to fade from
+        assert_eq!(
+            blend(
+                &layered_contributions(),
+                BlendMode::Crossfade { seconds: 1.0 }
+            ),
+            Vec3::NEG_X * 5.0
+        );
+    }
+
+    #[test]
+    fn priority_only_pulls_with_the_highest_layer() {
+        assert_eq!(
+            blend(&layered_contributions(), BlendMode::Priority),
+            Vec3::NEG_Y * 2.0
+        );
+
+        // Within a layer every source adds up
+        let mut contributions = layered_contributions();
+        contributions[0].priority = 0;
+        assert_eq!(
+            blend(&contributions, BlendMode::Priority),
+            Vec3::new(-5.0, -2.0, -3.0)
+        );
+    }
+
+    #[test]
+    fn nothing_to_blend_is_no_force() {
+        for mode in [
+            BlendMode::Sum,
+            BlendMode::Strongest,
+            BlendMode::Priority,
+            BlendMode::Crossfade { seconds: 1.0 },
+        ] {
+            assert_eq!(blend(&[], mode), Vec3::ZERO);
         }
+    }
+
+    #[test]
+    fn hysteresis_keeps_the_dominant_source_near_equal_strength() {
+        let blending = GravityBlending::new(BlendMode::Strongest).with_hysteresis(0.1);
+        let mut current = None;
 
-        let rotation_axis = player_up.cross(gravity_up);
+        // Two sources taking turns being slightly stronger, like a body
+        // sitting right between them
+        for step in 0..20 {
+            let wobble = if step % 2 == 0 { 0.3 } else { -0.3 };
+            let contributions = [
+                contribution(0, Vec3::NEG_Y * (10.0 + wobble), 0),
+                contribution(1, Vec3::Y * (10.0 - wobble), 0),
+            ];
 
-        if rotation_axis != Vec3::ZERO {
-            let rotation_axis = rotation_axis.normalize();
-            let rotation_diff = Quat::from_axis_angle(rotation_axis, rotation_angle.to_radians());
-            let target_rotation = rotation_diff * rotation.0;
+            let dominant = dominant_contribution(&contributions, current, &blending)
+                .map(|contribution| contribution.source);
 
-            // Gradually rotate the player to align with the gravity direction
-            rotation.0 = rotation.0.slerp(target_rotation, ROTATION_SPEED);
+            if step > 0 {
+                assert_eq!(dominant, current, "flipped on step {}", step);
+            }
+            current = dominant;
         }
+
+        assert_eq!(current, Some(Entity::from_raw(0)));
+
+        // Clearly beaten it hands over
+        let contributions = [
+            contribution(0, Vec3::NEG_Y * 10.0, 0),
+            contribution(1, Vec3::Y * 11.5, 0),
+        ];
+        assert_eq!(
+            dominant_contribution(&contributions, current, &blending)
+                .map(|contribution| contribution.source),
+            Some(Entity::from_raw(1))
+        );
+
+        // Without hysteresis it follows whichever is stronger
+        let blending = GravityBlending::new(BlendMode::Strongest);
+        let contributions = [
+            contribution(0, Vec3::NEG_Y * 9.9, 0),
+            contribution(1, Vec3::Y * 10.0, 0),
+        ];
+        assert_eq!(
+            dominant_contribution(&contributions, current, &blending)
+                .map(|contribution| contribution.source),
+            Some(Entity::from_raw(1))
+        );
+    }
+
+    #[test]
+    fn hysteresis_doesnt_hold_back_a_higher_layer() {
+        let blending = GravityBlending::new(BlendMode::Priority).with_hysteresis(10.0);
+        let contributions = layered_contributions();
+
+        assert_eq!(
+            dominant_contribution(&contributions, Some(Entity::from_raw(1)), &blending)
+                .map(|contribution| contribution.source),
+            Some(Entity::from_raw(0))
+        );
     }
 }
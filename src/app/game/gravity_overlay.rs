@@ -0,0 +1,173 @@
+//! A debug overlay for tuning gravity in levels. It shows the field around
+//! the camera, the volume of every gravity sensor and which source is pulling
+//! on the player.
+
+use bevy::prelude::*;
+use bevy_trait_query::WithOne;
+use bevy_xpbd_3d::prelude::*;
+
+use crate::app::player_input::{ui_action_just_pressed, UiAction};
+
+use super::{
+    game_state_machine::GameState,
+    gravity::{BlendMode, GravityBlending, GravityBound, GravitySource},
+    player::Player,
+    trajectory::GravityField,
+};
+
+pub struct GravityOverlayPlugin;
+
+impl Plugin for GravityOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GravityOverlay>().add_systems(
+            Update,
+            (
+                toggle_gravity_overlay
+                    .run_if(ui_action_just_pressed(UiAction::ToggleGravityOverlay)),
+                (draw_field, draw_sensors).run_if(overlay_enabled),
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct GravityOverlay {
+    pub enabled: bool,
+
+    /// Distance between samples of the field
+    pub spacing: f32,
+
+    /// How many samples there are on each side of the camera along each axis
+    pub samples: i32,
+
+    /// The strength that's drawn in the hottest color, for a body with a mass
+    /// of one
+    pub max_strength: f32,
+}
+
+impl Default for GravityOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            spacing: 4.0,
+            samples: 4,
+            max_strength: 30.0,
+        }
+    }
+}
+
+const SENSOR_COLOR: Color = Color::rgba(0.5, 0.5, 1.0, 0.5);
+const DOMINANT_COLOR: Color = Color::YELLOW;
+
+fn overlay_enabled(overlay: Res<GravityOverlay>) -> bool {
+    overlay.enabled
+}
+
+fn toggle_gravity_overlay(mut overlay: ResMut<GravityOverlay>) {
+    overlay.enabled = !overlay.enabled;
+}
+
+/// Samples the field on a grid around the camera. Arrows point along the
+/// pull and go from blue for weak to red for strong.
+fn draw_field(
+    overlay: Res<GravityOverlay>,
+    gravity_field: GravityField,
+    mut gizmos: Gizmos,
+    camera_query: Query<&GlobalTransform, With<Camera3d>>,
+) {
+    let Ok(camera_transform) = camera_query.get_single() else {
+        return;
+    };
+
+    // Snapping to the grid keeps the arrows still while the camera moves
+    let center = (camera_transform.translation() / overlay.spacing).round() * overlay.spacing;
+    let blending = GravityBlending::new(BlendMode::Sum);
+    let range = -overlay.samples..=overlay.samples;
+
+    for x in range.clone() {
+        for y in range.clone() {
+            for z in range.clone() {
+                let point = center + Vec3::new(x as f32, y as f32, z as f32) * overlay.spacing;
+                let force = gravity_field.force_at(point, 1.0, &blending);
+
+                if force == Vec3::ZERO {
+                    continue;
+                }
+
+                let strength = (force.length() / overlay.max_strength).clamp(0.0, 1.0);
+                let color = Color::hsl(240.0 * (1.0 - strength), 1.0, 0.5);
+
+                draw_arrow(
+                    &mut gizmos,
+                    point,
+                    force.normalize() * overlay.spacing * 0.5,
+                    color,
+                );
+            }
+        }
+    }
+}
+
+fn draw_arrow(gizmos: &mut Gizmos, start: Vec3, vector: Vec3, color: Color) {
+    let end = start + vector;
+    let head = vector * 0.3;
+    let side = vector.normalize().any_orthonormal_vector() * head.length() * 0.5;
+
+    gizmos.line(start, end, color);
+    gizmos.line(end, end - head + side, color);
+    gizmos.line(end, end - head - side, color);
+}
+
+/// Outlines every gravity sensor, and the one the player is bound to most.
+fn draw_sensors(
+    mut gizmos: Gizmos,
+    sensor_query: Query<
+        (Entity, &Position, &Rotation, &Collider),
+        (With<Sensor>, WithOne<dyn GravitySource>),
+    >,
+    player_query: Query<(&Position, &GravityBound), With<Player>>,
+) {
+    let dominant_sources: Vec<Entity> = player_query
+        .iter()
+        .filter_map(|(_, gravity_bound)| gravity_bound.dominant_source)
+        .collect();
+
+    for (entity, position, rotation, collider) in &sensor_query {
+        let color = if dominant_sources.contains(&entity) {
+            DOMINANT_COLOR
+        } else {
+            SENSOR_COLOR
+        };
+
+        let shape = collider.get_shape();
+
+        if let Some(ball) = shape.as_ball() {
+            gizmos.sphere(position.0, rotation.0, ball.radius, color);
+        } else {
+            // Anything that's not a sphere is outlined by its bounding box
+            let aabb = shape.compute_local_aabb();
+            let center: Vec3 = aabb.center().into();
+            let size: Vec3 = aabb.extents().into();
+
+            gizmos.cuboid(
+                Transform::from_translation(position.0 + rotation.0 * center)
+                    .with_rotation(rotation.0)
+                    .with_scale(size),
+                color,
+            );
+        }
+    }
+
+    for (player_position, gravity_bound) in &player_query {
+        let Some(Ok((_, source_position, ..))) = gravity_bound
+            .dominant_source
+            .map(|source| sensor_query.get(source))
+        else {
+            continue;
+        };
+
+        gizmos.line(player_position.0, source_position.0, DOMINANT_COLOR);
+    }
+}
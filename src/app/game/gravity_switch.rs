@@ -0,0 +1,262 @@
+//! Switches that change which way is down, for puzzles built around gravity.
+//! Planar gravity turns smoothly so [`keep_upright`](super::gravity::keep_upright)
+//! can follow it.
+
+use bevy::{
+    ecs::{
+        entity::{EntityMapper, MapEntities},
+        reflect::ReflectMapEntities,
+    },
+    prelude::*,
+};
+use bevy_xpbd_3d::{prelude::*, resources::DeltaTime, PhysicsSchedule};
+use leafwing_input_manager::prelude::ActionState;
+use serde::Deserialize;
+
+use crate::app::player_input::GameAction;
+
+use super::{
+    game_state_machine::GameState,
+    gravity::{GravityDisabled, GravitySystemSet, PlanarGravity},
+    player::Player,
+};
+
+pub struct GravitySwitchPlugin;
+
+impl Plugin for GravitySwitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<GravitySwitch>()
+            .register_type::<SwitchAction>()
+            .register_type::<Activation>()
+            .register_type::<PlanarGravityTurn>()
+            .add_event::<GravitySwitchedEvent>()
+            .add_systems(
+                Update,
+                (activate_switches, switch_gravity)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                PhysicsSchedule,
+                turn_planar_gravity
+                    .run_if(in_state(GameState::Playing))
+                    .before(GravitySystemSet),
+            );
+    }
+}
+
+/// A volume that changes the gravity of its targets when the player walks
+/// into it or interacts with it.
+#[derive(Component, Reflect, Default, Debug, Clone)]
+#[reflect(Component, MapEntities)]
+pub struct GravitySwitch {
+    /// Gravity sources, or bodies whose gravity sources are switched
+    pub targets: Vec<Entity>,
+
+    pub action: SwitchAction,
+
+    pub activation: Activation,
+
+    /// How long planar gravity takes to turn
+    pub seconds: f32,
+
+    /// Whether the player is inside the switch's volume
+    pub occupied: bool,
+}
+
+/// A [`GravitySwitch`] and the volume it's activated in. Unlike gravity
+/// sensors nothing is pulled by it.
+#[derive(Bundle)]
+pub struct GravitySwitchBundle {
+    pub switch: GravitySwitch,
+
+    pub position: Position,
+
+    pub rigid_body: RigidBody,
+
+    pub collider: Collider,
+
+    pub sensor: Sensor,
+
+    /// Whether the player is inside is read from this
+    pub colliding_entities: CollidingEntities,
+}
+
+impl MapEntities for GravitySwitch {
+    fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        for target in &mut self.targets {
+            *target = entity_mapper.get_or_reserve(*target);
+        }
+    }
+}
+
+#[derive(Reflect, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+pub enum SwitchAction {
+    /// Turns planar gravity upside down
+    #[default]
+    Flip,
+
+    /// Turns planar gravity around a world space axis
+    Rotate { axis: Vec3, degrees: f32 },
+
+    /// Turns gravity sources off, or back on
+    Toggle,
+}
+
+#[derive(Reflect, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+pub enum Activation {
+    /// Every time the player enters the volume
+    #[default]
+    Trigger,
+
+    /// When the player interacts while inside the volume
+    Interact,
+}
+
+/// Sent when a switch is activated.
+#[derive(Event)]
+pub struct GravitySwitchedEvent {
+    pub switch: Entity,
+}
+
+/// Planar gravity part way through turning to a new normal.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+pub struct PlanarGravityTurn {
+    pub from: Vec3,
+
+    pub axis: Vec3,
+
+    /// In radians
+    pub angle: f32,
+
+    pub elapsed: f32,
+
+    pub seconds: f32,
+}
+
+impl PlanarGravityTurn {
+    fn normal(&self) -> Vec3 {
+        let t = if self.seconds > 0.0 {
+            (self.elapsed / self.seconds).min(1.0)
+        } else {
+            1.0
+        };
+
+        // Smoothstep so the turn eases in and out
+        let t = t * t * (3.0 - 2.0 * t);
+
+        Quat::from_axis_angle(self.axis, self.angle * t) * self.from
+    }
+}
+
+fn activate_switches(
+    mut switch_query: Query<(Entity, &mut GravitySwitch, &CollidingEntities)>,
+    player_query: Query<&ActionState<GameAction>, With<Player>>,
+    mut switched_writer: EventWriter<GravitySwitchedEvent>,
+) {
+    for (switch, mut gravity_switch, colliding_entities) in &mut switch_query {
+        let players: Vec<&ActionState<GameAction>> = colliding_entities
+            .0
+            .iter()
+            .filter_map(|entity| player_query.get(*entity).ok())
+            .collect();
+        let occupied = !players.is_empty();
+        let entered = occupied && !gravity_switch.occupied;
+
+        if gravity_switch.occupied != occupied {
+            gravity_switch.occupied = occupied;
+        }
+
+        let activated = match gravity_switch.activation {
+            Activation::Trigger => entered,
+            Activation::Interact => players
+                .iter()
+                .any(|action_state| action_state.just_pressed(GameAction::Interact)),
+        };
+
+        if activated {
+            switched_writer.send(GravitySwitchedEvent { switch });
+        }
+    }
+}
+
+fn switch_gravity(
+    mut commands: Commands,
+    mut switched_reader: EventReader<GravitySwitchedEvent>,
+    switch_query: Query<&GravitySwitch>,
+    children_query: Query<&Children>,
+    source_query: Query<(Option<&PlanarGravity>, Option<&GravityDisabled>), With<Sensor>>,
+) {
+    for switched in switched_reader.iter() {
+        let Ok(gravity_switch) = switch_query.get(switched.switch) else {
+            continue;
+        };
+
+        // Targets are either sources themselves or bodies with sources as
+        // children
+        let sources = gravity_switch.targets.iter().flat_map(|&target| {
+            std::iter::once(target).chain(
+                children_query
+                    .get(target)
+                    .map(|children| children.to_vec())
+                    .unwrap_or_default(),
+            )
+        });
+
+        for source in sources {
+            let Ok((planar_gravity, disabled)) = source_query.get(source) else {
+                continue;
+            };
+
+            let turn = |normal: Vec3, axis: Vec3, angle: f32| PlanarGravityTurn {
+                from: normal,
+                axis,
+                angle,
+                elapsed: 0.0,
+                seconds: gravity_switch.seconds,
+            };
+
+            match (gravity_switch.action, planar_gravity) {
+                (SwitchAction::Flip, Some(planar_gravity)) => {
+                    let normal = planar_gravity.normal.normalize();
+                    commands.entity(source).insert(turn(
+                        normal,
+                        normal.any_orthonormal_vector(),
+                        std::f32::consts::PI,
+                    ));
+                }
+                (SwitchAction::Rotate { axis, degrees }, Some(planar_gravity)) => {
+                    commands.entity(source).insert(turn(
+                        planar_gravity.normal.normalize(),
+                        axis.normalize(),
+                        degrees.to_radians(),
+                    ));
+                }
+                (SwitchAction::Toggle, _) => {
+                    if disabled.is_some() {
+                        commands.entity(source).remove::<GravityDisabled>();
+                    } else {
+                        commands.entity(source).insert(GravityDisabled);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn turn_planar_gravity(
+    mut commands: Commands,
+    delta_time: Res<DeltaTime>,
+    mut turning_query: Query<(Entity, &mut PlanarGravity, &mut PlanarGravityTurn)>,
+) {
+    for (entity, mut planar_gravity, mut turn) in &mut turning_query {
+        turn.elapsed += delta_time.0;
+        planar_gravity.normal = turn.normal();
+
+        if turn.elapsed >= turn.seconds {
+            commands.entity(entity).remove::<PlanarGravityTurn>();
+        }
+    }
+}
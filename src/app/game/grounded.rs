@@ -0,0 +1,126 @@
+//! Works out once per physics step whether bodies are standing on something,
+//! so movement, animation and sounds all agree on it.
+
+use bevy::{
+    ecs::{
+        entity::{EntityMapper, MapEntities},
+        reflect::ReflectMapEntities,
+    },
+    prelude::*,
+};
+use bevy_xpbd_3d::{prelude::*, resources::DeltaTime, PhysicsSchedule};
+
+use super::{game_state_machine::GameState, gravity::GravityBound};
+
+pub struct GroundedPlugin;
+
+impl Plugin for GroundedPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Grounded>()
+            .add_event::<Landed>()
+            .add_event::<LeftGround>()
+            .add_systems(
+                PhysicsSchedule,
+                update_grounded
+                    .run_if(in_state(GameState::Playing))
+                    .in_set(GroundedSystemSet),
+            );
+    }
+}
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct GroundedSystemSet;
+
+/// What a body with a [`ShapeCaster`] pointing down is standing on. Sensors
+/// don't count as ground.
+#[derive(Component, Reflect, Default, Debug, Clone)]
+#[reflect(Component, MapEntities)]
+pub struct Grounded {
+    pub grounded: bool,
+
+    pub ground: Option<Entity>,
+
+    /// Points away from the ground, or zero in the air
+    pub normal: Vec3,
+
+    /// Angle in radians between the ground and the body's gravity
+    pub slope: f32,
+
+    /// Seconds since the body last touched the ground
+    pub air_time: f32,
+}
+
+impl MapEntities for Grounded {
+    fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        if let Some(ground) = &mut self.ground {
+            *ground = entity_mapper.get_or_reserve(*ground);
+        }
+    }
+}
+
+/// Sent when a body touches the ground after being in the air.
+#[derive(Event)]
+pub struct Landed {
+    pub entity: Entity,
+
+    pub ground: Entity,
+
+    /// How long the body was in the air for
+    pub air_time: f32,
+}
+
+/// Sent when a body leaves the ground, by jumping or falling off.
+#[derive(Event)]
+pub struct LeftGround {
+    pub entity: Entity,
+
+    pub ground: Entity,
+}
+
+fn update_grounded(
+    delta_time: Res<DeltaTime>,
+    mut grounded_query: Query<(Entity, &mut Grounded, &ShapeHits, Option<&GravityBound>)>,
+    sensors_query: Query<(), With<Sensor>>,
+    mut landed_writer: EventWriter<Landed>,
+    mut left_ground_writer: EventWriter<LeftGround>,
+) {
+    for (entity, mut grounded, shape_hits, gravity_bound) in &mut grounded_query {
+        let ground_hit = shape_hits
+            .iter()
+            .find(|hit| !sensors_query.contains(hit.entity));
+
+        let Some(ground_hit) = ground_hit else {
+            if let Some(ground) = grounded.ground {
+                left_ground_writer.send(LeftGround { entity, ground });
+            }
+
+            grounded.grounded = false;
+            grounded.ground = None;
+            grounded.normal = Vec3::ZERO;
+            grounded.slope = 0.0;
+            grounded.air_time += delta_time.0;
+            continue;
+        };
+
+        if !grounded.grounded {
+            landed_writer.send(Landed {
+                entity,
+                ground: ground_hit.entity,
+                air_time: grounded.air_time,
+            });
+        }
+
+        let normal = ground_hit.normal2.normalize_or_zero();
+        let up = gravity_bound.map_or(Vec3::ZERO, |gravity_bound| gravity_bound.up);
+
+        grounded.grounded = true;
+        grounded.ground = Some(ground_hit.entity);
+        grounded.normal = normal;
+        grounded.slope = if up == Vec3::ZERO || normal == Vec3::ZERO {
+            0.0
+        } else {
+            normal.angle_between(up)
+        };
+        grounded.air_time = 0.0;
+    }
+}
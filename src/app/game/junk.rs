@@ -1,21 +1,29 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashSet};
 use bevy_xpbd_3d::prelude::Collision;
 
-use super::{game_state_machine::GameState, Planet};
+use super::{game_state_machine::GameState, player::Player, Planet};
 
 pub struct JunkPlugin;
 
 impl Plugin for JunkPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<JunkCollisionEvent>().add_systems(
-            Update,
-            (junk_collisions,).run_if(in_state(GameState::Playing)),
-        );
+        app.register_type::<Junk>()
+            .register_type::<PushedByPlayer>()
+            .add_event::<JunkCollisionEvent>()
+            .add_event::<JunkCollectedEvent>()
+            .add_systems(
+                Update,
+                (junk_collisions, collect_junk).run_if(in_state(GameState::Playing)),
+            );
     }
 }
 
 #[derive(Event)]
 pub struct JunkCollisionEvent {
+    pub junk: Entity,
+
+    pub planet: Entity,
+
     pub normal: Vec3,
 
     pub penetration: f32,
@@ -24,9 +32,26 @@ pub struct JunkCollisionEvent {
     pub contact_point: Vec3,
 }
 
+/// Sent when the player touches a piece of junk and picks it up.
+#[derive(Event)]
+pub struct JunkCollectedEvent {
+    /// The junk entity. It's despawned by the time this is read.
+    pub junk: Entity,
+}
+
 #[derive(Component, Reflect, Default, Debug)]
 #[reflect(Component)]
-pub struct Junk {}
+pub struct Junk {
+    /// Whether the player picks this up by touching it. Junk that has to be
+    /// pushed around, e.g. to deliver it somewhere, isn't collectable.
+    pub collectable: bool,
+}
+
+/// Junk that the player has pushed at least once, which is what counts for
+/// [`ObjectiveDescriptor::DeliverJunk`](crate::app::game_levels::ObjectiveDescriptor::DeliverJunk).
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+pub struct PushedByPlayer;
 
 fn junk_collisions(
     mut collision_event_reader: EventReader<Collision>,
@@ -35,25 +60,65 @@ fn junk_collisions(
     junk_query: Query<Entity, With<Junk>>,
 ) {
     for Collision(contact) in collision_event_reader.iter() {
-        let is_planet_entity1 = planet_query.get(contact.entity1).is_ok();
-        let is_junk_entity2 = junk_query.get(contact.entity2).is_ok();
-
-        let is_junk_entity1 = junk_query.get(contact.entity1).is_ok();
-        let is_planet_entity2 = planet_query.get(contact.entity2).is_ok();
+        let (junk, planet) = if junk_query.contains(contact.entity1)
+            && planet_query.contains(contact.entity2)
+        {
+            (contact.entity1, contact.entity2)
+        } else if planet_query.contains(contact.entity1) && junk_query.contains(contact.entity2) {
+            (contact.entity2, contact.entity1)
+        } else {
+            continue;
+        };
 
         for manifold in &contact.manifolds {
             for contact in &manifold.contacts {
                 let contact_point = (contact.point1 + contact.point2) / 2.0;
 
-                if (is_planet_entity1 && is_junk_entity2) || (is_junk_entity1 && is_planet_entity2)
-                {
-                    junk_collision_write.send(JunkCollisionEvent {
-                        normal: contact.normal,
-                        penetration: contact.penetration,
-                        contact_point,
-                    });
-                }
+                junk_collision_write.send(JunkCollisionEvent {
+                    junk,
+                    planet,
+                    normal: contact.normal,
+                    penetration: contact.penetration,
+                    contact_point,
+                });
             }
         }
     }
 }
+
+/// Picks up collectable junk the player touches and marks any other junk as
+/// pushed.
+fn collect_junk(
+    mut commands: Commands,
+    mut collision_event_reader: EventReader<Collision>,
+    mut junk_collected_writer: EventWriter<JunkCollectedEvent>,
+    player_query: Query<Entity, With<Player>>,
+    junk_query: Query<&Junk>,
+) {
+    // There can be more than one collision with the same junk in a frame
+    let mut collected = HashSet::new();
+
+    for Collision(contact) in collision_event_reader.iter() {
+        let junk = if player_query.contains(contact.entity1) {
+            contact.entity2
+        } else if player_query.contains(contact.entity2) {
+            contact.entity1
+        } else {
+            continue;
+        };
+
+        let Ok(Junk { collectable }) = junk_query.get(junk) else {
+            continue;
+        };
+
+        if !collectable {
+            commands.entity(junk).insert(PushedByPlayer);
+            continue;
+        }
+
+        if collected.insert(junk) {
+            commands.entity(junk).despawn_recursive();
+            junk_collected_writer.send(JunkCollectedEvent { junk });
+        }
+    }
+}
@@ -0,0 +1,736 @@
+use bevy::{
+    gltf::{Gltf, GltfMesh},
+    prelude::*,
+};
+use bevy_xpbd_3d::prelude::{
+    AngularDamping, AngularVelocity, CoefficientCombine, Collider, ColliderMassProperties,
+    CollidingEntities, ExternalForce, Friction, Inertia, Mass, Position, Restitution, RigidBody,
+    Rotation, Sensor, ShapeCaster, SpatialQueryFilter,
+};
+use leafwing_input_manager::prelude::InputManagerBundle;
+
+use crate::{
+    app::{
+        game_levels::{
+            BlackHoleDescriptor, CurrentLevel, FieldDescriptor, FieldKind, FieldShape,
+            GravityDescriptor, GravityKind, JunkDescriptor, Level, MotionDescriptor,
+            PlanetDescriptor, PlatformDescriptor, SwitchDescriptor, TubeDescriptor,
+        },
+        player_input::GameAction,
+    },
+    assets::{
+        characters::AstronautCollection, environment::PlanetCollection, items::ItemCollection,
+        levels::LevelCollection,
+    },
+    saving::SaveId,
+    utility::collider_from_gltf,
+};
+
+use super::{
+    black_hole::{BlackHole, Swallow},
+    force_fields::{Drag, Repulsor, Vortex, Wind},
+    graphics::MainFollowTarget,
+    gravity::{
+        BlendMode, CurvedGravity, GravityBlending, GravityBound, GravityFalloff, GravityPriority,
+        GravitySourceBundle, PlanarGravity, PointGravity, SurfaceGravity, Upright,
+        UprightAlignment, UprightRecovery,
+    },
+    gravity_switch::{Activation, GravitySwitch, GravitySwitchBundle},
+    grounded::Grounded,
+    junk::Junk,
+    motion::{Motion, MotionPath},
+    player::Player,
+    void::Drifting,
+    InGameLevelMarker, MovementState, Planet, PlanetBundle,
+};
+
+/// Spawns everything described by a [`Level`] asset into the world.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_level(
+    mut commands: Commands,
+    current_level: Res<CurrentLevel>,
+    level_collection: Res<LevelCollection>,
+    levels: Res<Assets<Level>>,
+    planet_collection: Res<PlanetCollection>,
+    astronaut_collection: Res<AstronautCollection>,
+    item_collection: Res<ItemCollection>,
+    gltf_assets: Res<Assets<Gltf>>,
+    gltf_meshes: Res<Assets<GltfMesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(level) = level_collection
+        .levels
+        .get(current_level.0)
+        .and_then(|handle| levels.get(handle))
+    else {
+        error!("No level to spawn at index {}", current_level.0);
+        return;
+    };
+
+    let mut named = Vec::new();
+    let mut save_id = SaveId::default();
+
+    for platform in &level.platforms {
+        let entity = spawn_platform(&mut commands, &mut meshes, platform, &mut save_id);
+        named.extend(platform.name.iter().map(|name| (name, entity)));
+    }
+
+    let mut planets = Vec::with_capacity(level.planets.len());
+    for planet in &level.planets {
+        let planet_gltf = planet.planet_type.model_from(&planet_collection);
+        let (scene, collider) =
+            collider_from_gltf(planet_gltf, &gltf_assets, &gltf_meshes, &meshes);
+
+        let entity = spawn_planet(&mut commands, planet, scene, collider, &mut save_id);
+        named.extend(planet.name.iter().map(|name| (name, entity)));
+        planets.push(entity);
+    }
+
+    // Planets can orbit each other so they all need to exist first
+    for (planet, entity) in level.planets.iter().zip(&planets) {
+        if let Some(motion) = &planet.motion {
+            if let Some(motion) = planet_motion(planet, motion, &level.planets, &planets) {
+                commands.entity(*entity).insert(motion);
+            }
+        }
+    }
+
+    for tube in &level.tubes {
+        spawn_tube(&mut commands, &mut meshes, tube, &mut save_id);
+    }
+
+    for field in &level.fields {
+        spawn_field(&mut commands, field, &mut save_id);
+    }
+
+    for black_hole in &level.black_holes {
+        spawn_black_hole(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            black_hole,
+            &mut save_id,
+        );
+    }
+
+    for switch in &level.switches {
+        spawn_switch(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            switch,
+            &named,
+            &mut save_id,
+        );
+    }
+
+    for junk in &level.junk {
+        spawn_junk(&mut commands, &item_collection, junk, &mut save_id);
+    }
+
+    spawn_player(
+        &mut commands,
+        astronaut_collection.fernando_the_flamingo.clone(),
+        level.player_spawn,
+        &mut save_id,
+    );
+}
+
+fn spawn_platform(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    platform: &PlatformDescriptor,
+    save_id: &mut SaveId,
+) -> Entity {
+    let mut platform_commands = commands.spawn_empty();
+
+    if let Some(name) = &platform.name {
+        platform_commands.insert(Name::new(name.clone()));
+    }
+
+    platform_commands
+        .insert((
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Plane {
+                    size: platform.size,
+                    subdivisions: 2,
+                })),
+                ..Default::default()
+            },
+            Position(platform.position),
+            RigidBody::Kinematic,
+            Mass(platform.mass),
+            ColliderMassProperties::ZERO,
+            Collider::cuboid(platform.size, 0.1, platform.size),
+            Restitution::new(0.0).with_combine_rule(CoefficientCombine::Max),
+            InGameLevelMarker,
+            save_id.next(),
+        ))
+        // The gravity field for this planar surface
+        .with_children(|parent| {
+            spawn_gravity_source(
+                parent,
+                platform.position,
+                Vec3::ZERO,
+                platform.mass,
+                &Collider::cuboid(platform.size, 0.1, platform.size),
+                &platform.gravity,
+                save_id,
+            );
+        })
+        .id()
+}
+
+fn spawn_planet(
+    commands: &mut Commands,
+    planet: &PlanetDescriptor,
+    scene: Handle<Scene>,
+    collider: Collider,
+    save_id: &mut SaveId,
+) -> Entity {
+    let body_collider = collider.clone();
+    let mut planet_commands = commands.spawn_empty();
+
+    if let Some(name) = &planet.name {
+        planet_commands.insert(Name::new(name.clone()));
+    }
+
+    planet_commands
+        .insert((
+            PlanetBundle {
+                planet: Planet {
+                    planet_type: planet.planet_type,
+                    state: MovementState::Idle,
+                },
+                position: Position(planet.position),
+                rigid_body: RigidBody::Kinematic,
+                mass: Mass(planet.mass),
+                friction: Friction::new(planet.friction.dynamic_coefficient)
+                    .with_static_coefficient(planet.friction.static_coefficient),
+                scene: SceneBundle { scene, ..default() },
+                collider_mass_properties: ColliderMassProperties::ZERO,
+                // TODO: How do you scale colliders?
+                collider,
+            },
+            Restitution::new(0.0).with_combine_rule(CoefficientCombine::Max),
+            AngularVelocity(planet.angular_velocity),
+            InGameLevelMarker,
+            save_id.next(),
+        ))
+        .with_children(|parent| {
+            spawn_gravity_source(
+                parent,
+                planet.position,
+                planet.angular_velocity,
+                planet.mass,
+                &body_collider,
+                &planet.gravity,
+                save_id,
+            );
+        })
+        .id()
+}
+
+/// Orbits keep the distance between the planet and what it orbits that the
+/// level starts with.
+fn planet_motion(
+    planet: &PlanetDescriptor,
+    motion: &MotionDescriptor,
+    planet_descriptors: &[PlanetDescriptor],
+    planet_entities: &[Entity],
+) -> Option<Motion> {
+    let path = match motion {
+        MotionDescriptor::Orbit {
+            center,
+            around,
+            axis,
+            period,
+        } => {
+            let (center, around) = match around {
+                Some(name) => {
+                    let Some(index) = planet_descriptors
+                        .iter()
+                        .position(|other| other.name.as_ref() == Some(name))
+                    else {
+                        warn!("Can't orbit around {}, no planet has that name", name);
+                        return None;
+                    };
+
+                    (
+                        planet_descriptors[index].position,
+                        Some(planet_entities[index]),
+                    )
+                }
+                None => (*center, None),
+            };
+
+            MotionPath::Orbit {
+                center,
+                around,
+                offset: planet.position - center,
+                axis: *axis,
+                period: *period,
+            }
+        }
+        MotionDescriptor::Path {
+            points,
+            speed,
+            looping,
+        } => MotionPath::Path {
+            points: points.clone(),
+            speed: *speed,
+            looping: *looping,
+        },
+    };
+
+    Some(Motion::new(path))
+}
+
+/// How many straight pieces each segment of a tube's curve is made of
+const TUBE_SUBDIVISIONS: usize = 8;
+
+fn spawn_tube(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    tube: &TubeDescriptor,
+    save_id: &mut SaveId,
+) {
+    let Some(curve) = tube.curve.to_curve() else {
+        warn!("Skipping a tube without enough points to make a curve");
+        return;
+    };
+
+    let points: Vec<Vec3> = curve
+        .iter_positions(tube.curve.segment_count() * TUBE_SUBDIVISIONS)
+        .collect();
+
+    commands
+        .spawn((
+            SpatialBundle::default(),
+            Position(tube.position),
+            RigidBody::Kinematic,
+            ColliderMassProperties::ZERO,
+            tube_collider(&points, tube.thickness),
+            Restitution::new(0.0).with_combine_rule(CoefficientCombine::Max),
+            InGameLevelMarker,
+            save_id.next(),
+        ))
+        .with_children(|parent| {
+            for piece in points.windows(2) {
+                let (start, end) = (piece[0], piece[1]);
+
+                parent.spawn(PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Capsule {
+                        radius: tube.thickness,
+                        depth: start.distance(end),
+                        ..default()
+                    })),
+                    transform: Transform::from_translation((start + end) / 2.0)
+                        .with_rotation(Quat::from_rotation_arc(Vec3::Y, (end - start).normalize())),
+                    ..default()
+                });
+            }
+
+            parent.spawn((
+                CurvedGravity::new(tube.curve.clone(), tube.gravity_strength).with_falloff(
+                    tube.gravity_falloff
+                        .as_ref()
+                        .map(|falloff| falloff.to_falloff(tube.gravity_radius))
+                        .unwrap_or_default(),
+                ),
+                GravitySourceBundle {
+                    position: Position(tube.position),
+                    rigid_body: RigidBody::Kinematic,
+                    collider: tube_collider(&points, tube.gravity_radius),
+                    sensor: Sensor,
+                },
+                save_id.next(),
+            ));
+        });
+}
+
+/// Capsules around each straight piece of a tube
+fn tube_collider(points: &[Vec3], radius: f32) -> Collider {
+    Collider::compound(
+        points
+            .windows(2)
+            .map(|piece| {
+                (
+                    Position::default(),
+                    Rotation::default(),
+                    Collider::capsule_endpoints(piece[0], piece[1], radius),
+                )
+            })
+            .collect(),
+    )
+}
+
+fn spawn_gravity_source(
+    parent: &mut ChildBuilder,
+    body_position: Vec3,
+    body_angular_velocity: Vec3,
+    body_mass: f32,
+    body_collider: &Collider,
+    gravity: &GravityDescriptor,
+    save_id: &mut SaveId,
+) {
+    let mut source_commands = match gravity.kind {
+        GravityKind::Point => parent.spawn((
+            PointGravity {
+                center_mass: body_mass,
+                gravity_strength: gravity.strength,
+                falloff: gravity
+                    .falloff
+                    .as_ref()
+                    .map(|falloff| falloff.to_falloff(gravity.radius))
+                    .unwrap_or_else(|| GravityFalloff::inverse_square(1.0)),
+            },
+            GravitySourceBundle {
+                position: Position(body_position),
+                rigid_body: RigidBody::Kinematic,
+                collider: Collider::ball(gravity.radius),
+                sensor: Sensor,
+            },
+        )),
+        GravityKind::Planar { normal } => {
+            let normal = normal.normalize();
+            let side = gravity.radius * 2.0;
+
+            // The sensor sits on top of the surface so we need to move it up
+            // by half its size
+            parent.spawn((
+                PlanarGravity {
+                    normal,
+                    gravity_strength: gravity.strength,
+                    plane_offset: gravity.radius,
+                    falloff: gravity
+                        .falloff
+                        .as_ref()
+                        .map(|falloff| falloff.to_falloff(side))
+                        .unwrap_or_default(),
+                },
+                GravitySourceBundle {
+                    position: Position(body_position + normal * gravity.radius),
+                    rigid_body: RigidBody::Kinematic,
+                    collider: Collider::cuboid(side, side, side),
+                    sensor: Sensor,
+                },
+            ))
+        }
+        GravityKind::Surface { sdf_cell_size } => {
+            let surface_gravity = SurfaceGravity::new(body_collider.clone(), gravity.strength)
+                .with_falloff(
+                    gravity
+                        .falloff
+                        .as_ref()
+                        .map(|falloff| falloff.to_falloff(gravity.radius))
+                        .unwrap_or_default(),
+                );
+
+            let distance_field_task = sdf_cell_size
+                .map(|cell_size| surface_gravity.bake_distance_field(gravity.radius, cell_size));
+
+            let mut surface_commands = parent.spawn((
+                surface_gravity,
+                GravitySourceBundle {
+                    position: Position(body_position),
+                    rigid_body: RigidBody::Kinematic,
+                    collider: Collider::ball(gravity.radius),
+                    sensor: Sensor,
+                },
+            ));
+
+            if let Some(distance_field_task) = distance_field_task {
+                surface_commands.insert(distance_field_task);
+            }
+
+            surface_commands
+        }
+    };
+
+    // Shaped gravity has to spin along with its body
+    source_commands.insert((
+        GravityPriority(gravity.priority),
+        AngularVelocity(body_angular_velocity),
+        save_id.next(),
+    ));
+}
+
+fn shape_collider(shape: &FieldShape) -> Collider {
+    match *shape {
+        FieldShape::Sphere { radius } => Collider::ball(radius),
+        FieldShape::Box { size } => Collider::cuboid(size.x, size.y, size.z),
+    }
+}
+
+fn spawn_field(commands: &mut Commands, field: &FieldDescriptor, save_id: &mut SaveId) {
+    let mut field_commands = commands.spawn((
+        GravitySourceBundle {
+            position: Position(field.position),
+            rigid_body: RigidBody::Kinematic,
+            collider: shape_collider(&field.shape),
+            sensor: Sensor,
+        },
+        InGameLevelMarker,
+        save_id.next(),
+    ));
+
+    match &field.kind {
+        FieldKind::Wind {
+            direction,
+            strength,
+        } => field_commands.insert(Wind {
+            direction: *direction,
+            strength: *strength,
+        }),
+        FieldKind::Vortex {
+            axis,
+            strength,
+            pull,
+        } => field_commands.insert(Vortex {
+            axis: *axis,
+            strength: *strength,
+            pull: *pull,
+        }),
+        FieldKind::Drag { coefficient } => field_commands.insert(Drag {
+            coefficient: *coefficient,
+        }),
+        FieldKind::Repulsor { strength, falloff } => field_commands.insert(Repulsor {
+            strength: *strength,
+            falloff: falloff
+                .as_ref()
+                .map(|falloff| falloff.to_falloff(field.shape.extent()))
+                .unwrap_or_default(),
+        }),
+    };
+}
+
+fn spawn_black_hole(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    black_hole: &BlackHoleDescriptor,
+    save_id: &mut SaveId,
+) {
+    commands
+        .spawn((
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::UVSphere {
+                    radius: black_hole.event_horizon,
+                    ..default()
+                })),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::BLACK,
+                    unlit: true,
+                    ..default()
+                }),
+                transform: Transform::from_translation(black_hole.position),
+                ..default()
+            },
+            Position(black_hole.position),
+            BlackHole {
+                event_horizon: black_hole.event_horizon,
+                stretch_radius: black_hole.stretch_radius,
+                swallow: black_hole
+                    .teleport_to
+                    .map_or(Swallow::Despawn, Swallow::Teleport),
+            },
+            InGameLevelMarker,
+            save_id.next(),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                PointGravity {
+                    center_mass: black_hole.mass,
+                    gravity_strength: black_hole.gravity_strength,
+                    falloff: GravityFalloff::inverse_square(black_hole.event_horizon),
+                },
+                GravitySourceBundle {
+                    position: Position(black_hole.position),
+                    rigid_body: RigidBody::Kinematic,
+                    collider: Collider::ball(black_hole.gravity_radius),
+                    sensor: Sensor,
+                },
+                save_id.next(),
+            ));
+        });
+}
+
+fn spawn_switch(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    switch: &SwitchDescriptor,
+    named: &[(&String, Entity)],
+    save_id: &mut SaveId,
+) {
+    let targets = switch
+        .targets
+        .iter()
+        .filter_map(|target| {
+            let entity = named
+                .iter()
+                .find(|(name, _)| *name == target)
+                .map(|(_, entity)| *entity);
+
+            if entity.is_none() {
+                warn!(
+                    "Can't switch the gravity of {}, nothing has that name",
+                    target
+                );
+            }
+
+            entity
+        })
+        .collect();
+
+    let mut switch_commands = commands.spawn((
+        GravitySwitchBundle {
+            switch: GravitySwitch {
+                targets,
+                action: switch.action,
+                activation: switch.activation,
+                seconds: switch.seconds,
+                occupied: false,
+            },
+            position: Position(switch.position),
+            rigid_body: RigidBody::Kinematic,
+            collider: shape_collider(&switch.shape),
+            sensor: Sensor,
+            colliding_entities: CollidingEntities::default(),
+        },
+        InGameLevelMarker,
+        save_id.next(),
+    ));
+
+    // Triggers are invisible but switches the player interacts with need to
+    // be found
+    if switch.activation == Activation::Interact {
+        switch_commands.insert(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cube { size: 0.4 })),
+            material: materials.add(StandardMaterial {
+                emissive: Color::rgb(1.0, 0.6, 0.1),
+                ..default()
+            }),
+            transform: Transform::from_translation(switch.position),
+            ..default()
+        });
+    }
+}
+
+fn spawn_junk(
+    commands: &mut Commands,
+    item_collection: &ItemCollection,
+    junk: &JunkDescriptor,
+    save_id: &mut SaveId,
+) {
+    commands
+        .spawn((
+            SpatialBundle::default(),
+            RigidBody::Dynamic,
+            Position(junk.position),
+            Collider::ball(junk.radius),
+            Mass(junk.mass),
+            ColliderMassProperties::ZERO,
+            Inertia(Mat3::IDENTITY),
+            Friction::new(0.6),
+            ExternalForce::default().with_persistence(false),
+            GravityBound::default(),
+            GravityBlending::new(BlendMode::Strongest).with_hysteresis(0.25),
+            Junk {
+                collectable: junk.collectable,
+            },
+            InGameLevelMarker,
+            save_id.next(),
+        ))
+        .with_children(|parent| {
+            parent.spawn(SceneBundle {
+                scene: junk.item_type.model_from(item_collection),
+                ..default()
+            });
+        });
+}
+
+fn spawn_player(
+    commands: &mut Commands,
+    astronaut: Handle<Scene>,
+    player_position: Vec3,
+    save_id: &mut SaveId,
+) {
+    let collider = Collider::ball(0.3);
+    // let direction_to_center = (player_position - planet_position).normalize();
+    // let rotation_axis = Vec3::Y.cross(direction_to_center).normalize();
+    // let rotation_angle = direction_to_center.angle_between(Vec3::Y);
+    // let rotation_quat = Quat::from_axis_angle(rotation_axis, rotation_angle);
+
+    let mut player_commands = commands.spawn_empty();
+    let player_id = player_commands.id();
+
+    player_commands
+        .insert((
+            // Adding this next line breaks for some reason :(
+            // Rotation(rotation_quat),
+            SpatialBundle::default(),
+            RigidBody::Dynamic,
+            Position(player_position),
+            collider.clone(),
+            // Cast the player shape downwards to detect when the player is grounded
+            ShapeCaster::new(
+                Collider::capsule(0.9, 0.35),
+                Vec3::ZERO,
+                // Vec3::Y * 0.05,
+                Quat::default(),
+                -Vec3::Y,
+            )
+            .with_ignore_origin_penetration(true) // Don't count player's collider
+            .with_max_hits(3)
+            .with_query_filter(SpatialQueryFilter::new().without_entities([player_id]))
+            .with_max_time_of_impact(0.2),
+            Restitution::new(0.0).with_combine_rule(CoefficientCombine::Min),
+            ColliderMassProperties::ZERO,
+            Inertia(Mat3::IDENTITY),
+            Friction::new(0.6),
+            Mass(1.0),
+            save_id.next(),
+            (
+                ExternalForce::default().with_persistence(false),
+                MainFollowTarget,
+                Player,
+                InGameLevelMarker,
+                GravityBound::default(),
+                // Walking from one world to another shouldn't pull the player
+                // sideways
+                GravityBlending::new(BlendMode::Crossfade { seconds: 0.5 }).with_hysteresis(0.25),
+                Drifting::default(),
+                Grounded::default(),
+                InputManagerBundle {
+                    input_map: GameAction::default_input_map(),
+                    ..default()
+                },
+                // Follow the slope of the ground and get back up after being
+                // knocked over
+                Upright {
+                    recovery: UprightRecovery::After {
+                        seconds: 1.0,
+                        speed: 4.0,
+                    },
+                    align_to: UprightAlignment::Ground,
+                    ..default()
+                },
+                // TODO: Not sure if we should use Linear damping or Angular
+                // damping here because we have funky axes and stuff.
+                AngularDamping(1.6),
+            ),
+        ))
+        .with_children(|parent| {
+            let mut transform = Transform::from_xyz(0.0, -0.35, 0.0).with_scale(Vec3::splat(0.3));
+            transform.rotate_y(std::f32::consts::PI);
+
+            parent.spawn(SceneBundle {
+                scene: astronaut,
+                transform,
+                ..default()
+            });
+        });
+}
@@ -0,0 +1,283 @@
+use bevy::{
+    ecs::{
+        entity::{EntityMapper, MapEntities},
+        reflect::ReflectMapEntities,
+    },
+    prelude::*,
+};
+use bevy_xpbd_3d::{prelude::*, resources::DeltaTime, PhysicsSchedule};
+
+use super::{game_state_machine::GameState, movement::MovementSystemSet, MovementState, Planet};
+
+pub struct MotionPlugin;
+
+impl Plugin for MotionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Motion>()
+            .register_type::<MotionPath>()
+            .add_systems(
+                PhysicsSchedule,
+                (move_bodies, follow_bodies)
+                    .chain()
+                    .run_if(in_state(GameState::Playing))
+                    .before(MovementSystemSet),
+            );
+    }
+}
+
+/// Moves a kinematic body, and its gravity sensors, along a path.
+#[derive(Component, Reflect, Default, Debug, Clone)]
+#[reflect(Component, MapEntities)]
+pub struct Motion {
+    pub path: MotionPath,
+
+    /// Seconds since the body started moving
+    pub elapsed: f32,
+}
+
+impl Motion {
+    pub fn new(path: MotionPath) -> Self {
+        Self { path, elapsed: 0.0 }
+    }
+}
+
+impl MapEntities for Motion {
+    fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        if let MotionPath::Orbit {
+            around: Some(around),
+            ..
+        } = &mut self.path
+        {
+            *around = entity_mapper.get_or_reserve(*around);
+        }
+    }
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub enum MotionPath {
+    /// Circles around a point, or around another body if given, keeping the
+    /// offset the body started at.
+    Orbit {
+        center: Vec3,
+        around: Option<Entity>,
+        offset: Vec3,
+        axis: Vec3,
+        /// Seconds per revolution
+        period: f32,
+    },
+
+    /// Follows straight lines between points, then either loops back to the
+    /// first point or turns around.
+    Path {
+        points: Vec<Vec3>,
+        speed: f32,
+        looping: bool,
+    },
+}
+
+impl Default for MotionPath {
+    fn default() -> Self {
+        MotionPath::Path {
+            points: Vec::new(),
+            speed: 0.0,
+            looping: false,
+        }
+    }
+}
+
+impl MotionPath {
+    /// Where the body should be `elapsed` seconds in, given where the body it
+    /// orbits is.
+    pub fn position(&self, elapsed: f32, center: Vec3) -> Option<Vec3> {
+        match self {
+            MotionPath::Orbit {
+                offset,
+                axis,
+                period,
+                ..
+            } => {
+                if *period <= 0.0 {
+                    return Some(center + *offset);
+                }
+
+                let angle = std::f32::consts::TAU * elapsed / *period;
+                let rotation = Quat::from_axis_angle(axis.normalize_or_zero(), angle);
+
+                Some(center + rotation * *offset)
+            }
+            MotionPath::Path {
+                points,
+                speed,
+                looping,
+            } => path_position(points, *speed * elapsed, *looping),
+        }
+    }
+}
+
+/// The velocity of a point on the surface of a moving, spinning body.
+pub fn surface_velocity(
+    linear_velocity: Vec3,
+    angular_velocity: Vec3,
+    center: Vec3,
+    point: Vec3,
+) -> Vec3 {
+    linear_velocity + angular_velocity.cross(point - center)
+}
+
+fn path_position(points: &[Vec3], distance: f32, looping: bool) -> Option<Vec3> {
+    let (&first, &last) = (points.first()?, points.last()?);
+
+    let mut legs: Vec<(Vec3, Vec3)> = points.windows(2).map(|leg| (leg[0], leg[1])).collect();
+    if looping {
+        legs.push((last, first));
+    }
+
+    let length: f32 = legs.iter().map(|(start, end)| start.distance(*end)).sum();
+    if length <= 0.0 {
+        return Some(first);
+    }
+
+    let mut distance = if looping {
+        distance.rem_euclid(length)
+    } else {
+        // Back and forth
+        let distance = distance.rem_euclid(length * 2.0);
+        if distance > length {
+            length * 2.0 - distance
+        } else {
+            distance
+        }
+    };
+
+    for (start, end) in legs {
+        let leg_length = start.distance(end);
+
+        if distance <= leg_length {
+            return Some(start.lerp(end, distance / leg_length.max(f32::EPSILON)));
+        }
+
+        distance -= leg_length;
+    }
+
+    Some(last)
+}
+
+/// Kinematic bodies are moved through their velocity rather than their
+/// position so the physics engine carries whatever rests on them.
+fn move_bodies(
+    delta_time: Res<DeltaTime>,
+    mut moving_query: Query<(
+        &mut Motion,
+        &Position,
+        &mut LinearVelocity,
+        Option<&mut Planet>,
+    )>,
+    center_query: Query<&Position>,
+) {
+    let delta = delta_time.0;
+    if delta <= 0.0 {
+        return;
+    }
+
+    for (mut motion, position, mut linear_velocity, planet) in &mut moving_query {
+        let center = match motion.path {
+            MotionPath::Orbit {
+                around: Some(around),
+                ..
+            } => center_query.get(around).map(|position| position.0).ok(),
+            MotionPath::Orbit { center, .. } => Some(center),
+            MotionPath::Path { .. } => Some(Vec3::ZERO),
+        };
+
+        motion.elapsed += delta;
+
+        let velocity = center
+            .and_then(|center| motion.path.position(motion.elapsed, center))
+            .map_or(Vec3::ZERO, |target| (target - position.0) / delta);
+
+        linear_velocity.0 = velocity;
+
+        if let Some(mut planet) = planet {
+            planet.state = if velocity == Vec3::ZERO {
+                MovementState::Idle
+            } else {
+                MovementState::Moving { velocity }
+            };
+        }
+    }
+}
+
+/// Where a sensor sits relative to the body it's a child of.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BodyOffset {
+    pub translation: Vec3,
+
+    pub rotation: Quat,
+}
+
+/// Puts child sensors, like gravity fields, back in place on their body every
+/// step, as integrating their own velocities slowly drifts away from it.
+fn follow_bodies(
+    mut commands: Commands,
+    body_query: Query<
+        (
+            &Position,
+            &Rotation,
+            &LinearVelocity,
+            &AngularVelocity,
+            &Children,
+        ),
+        Without<Sensor>,
+    >,
+    mut sensor_query: Query<
+        (
+            Entity,
+            &mut Position,
+            &mut Rotation,
+            &mut LinearVelocity,
+            &mut AngularVelocity,
+            Option<&BodyOffset>,
+        ),
+        With<Sensor>,
+    >,
+) {
+    for (body_position, body_rotation, body_linear_velocity, body_angular_velocity, children) in
+        &body_query
+    {
+        for &child in children.iter() {
+            let Ok((
+                sensor,
+                mut position,
+                mut rotation,
+                mut linear_velocity,
+                mut angular_velocity,
+                body_offset,
+            )) = sensor_query.get_mut(child)
+            else {
+                continue;
+            };
+
+            // Sensors start out where they belong
+            let body_offset = body_offset.copied().unwrap_or_else(|| {
+                let inverse_rotation = body_rotation.0.inverse();
+                let body_offset = BodyOffset {
+                    translation: inverse_rotation * (position.0 - body_position.0),
+                    rotation: inverse_rotation * rotation.0,
+                };
+
+                commands.entity(sensor).insert(body_offset);
+                body_offset
+            });
+
+            let offset = body_rotation.0 * body_offset.translation;
+
+            position.0 = body_position.0 + offset;
+            rotation.0 = body_rotation.0 * body_offset.rotation;
+
+            // Moves along with the body during the step, swinging around it
+            // as it turns
+            linear_velocity.0 = body_linear_velocity.0 + body_angular_velocity.0.cross(offset);
+            angular_velocity.0 = body_angular_velocity.0;
+        }
+    }
+}
@@ -1,7 +1,13 @@
 use bevy::prelude::*;
 use bevy_xpbd_3d::{prelude::*, PhysicsSchedule};
+use leafwing_input_manager::prelude::ActionState;
 
-use super::{game_state_machine::GameState, gravity::GravityBound, player::Player};
+use crate::app::player_input::{action_axis, GameAction};
+
+use super::{
+    game_state_machine::GameState, gravity::GravityBound, grounded::Grounded,
+    motion::surface_velocity, player::Player,
+};
 
 pub struct MovementPlugin;
 
@@ -28,10 +34,10 @@ impl Plugin for MovementPlugin {
 pub struct MovementSystemSet;
 
 const PLAYER_SPEED: f32 = 6.4;
+pub const JUMP_IMPULSE: f32 = 16.0;
 const AIR_CONTROL_FACTOR: f32 = 0.2;
 
 pub fn movement(
-    keyboard_input: Res<Input<KeyCode>>,
     // debug_gizmos: Res<DebugGizmos>,
     mut gizmos: Gizmos,
     mut players: Query<
@@ -39,36 +45,24 @@ pub fn movement(
             &Transform,
             &mut Rotation,
             &mut LinearVelocity,
-            &ShapeHits,
+            &Grounded,
             &GravityBound,
+            &ActionState<GameAction>,
         ),
         With<Player>,
     >,
-    sensors_query: Query<&Sensor>,
 ) {
-    for (transform, mut rotation, mut linear_velocity, shape_hits, gravity_bound) in &mut players {
+    for (transform, mut rotation, mut linear_velocity, grounded, gravity_bound, action_state) in
+        &mut players
+    {
         let gravity_force = gravity_bound.gravity_force;
         let gravity_up = -gravity_force.normalize();
-        let touching_ground = shape_hits
-            .iter()
-            .any(|hit| sensors_query.get(hit.entity).is_err());
         let forward = transform.forward();
         let right = transform.right();
-        // Create a movement vector from the keyboard input
-        let mut move_dir = Vec3::ZERO;
-
-        if keyboard_input.pressed(KeyCode::Up) {
-            move_dir += forward;
-        }
-        if keyboard_input.pressed(KeyCode::Down) {
-            move_dir -= forward;
-        }
-        if keyboard_input.pressed(KeyCode::Left) {
-            move_dir -= right;
-        }
-        if keyboard_input.pressed(KeyCode::Right) {
-            move_dir += right;
-        }
+        // Create a movement vector from the stick or keys, a stick that's only
+        // pushed part way moves the player slower
+        let input = action_axis(action_state, GameAction::Move);
+        let mut move_dir = forward * input.y + right * input.x;
 
         if move_dir != Vec3::ZERO {
             move_dir = move_dir.normalize();
@@ -89,7 +83,7 @@ pub fn movement(
                 rotation.0 = new_rotation;
             }
 
-            move_dir = move_dir * PLAYER_SPEED;
+            move_dir = move_dir * PLAYER_SPEED * input.length();
 
             // If the character is floating in space, don't apply movement
             if gravity_force == Vec3::ZERO {
@@ -97,7 +91,7 @@ pub fn movement(
             }
 
             // Apply the movement to the player
-            if touching_ground {
+            if grounded.grounded {
                 linear_velocity.0 += move_dir;
             } else {
                 // apply smaller movement when in air
@@ -108,11 +102,17 @@ pub fn movement(
 }
 
 pub fn jump(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut players: Query<(&mut ExternalImpulse, &ShapeHits, &GravityBound), With<Player>>,
-    sensors_query: Query<&Sensor>,
+    mut players: Query<
+        (
+            &mut ExternalImpulse,
+            &Grounded,
+            &GravityBound,
+            &ActionState<GameAction>,
+        ),
+        With<Player>,
+    >,
 ) {
-    for (mut external_impulse, shape_hits, gravity_bound) in &mut players {
+    for (mut external_impulse, grounded, gravity_bound, action_state) in &mut players {
         let gravity_force = gravity_bound.gravity_force;
 
         // If the player is floating in space, don't apply jump
@@ -120,20 +120,16 @@ pub fn jump(
             continue;
         }
 
-        let touching_ground = shape_hits
-            .iter()
-            .any(|hit| sensors_query.get(hit.entity).is_err());
-
-        if !touching_ground {
+        if !grounded.grounded {
             continue;
         }
 
         let gravity_up = -gravity_force.normalize();
 
-        if keyboard_input.just_pressed(KeyCode::Space) {
+        if action_state.just_pressed(GameAction::Jump) {
             // TODO: The amount of impulse should be inversely proportional to the
             // gravity force
-            external_impulse.apply_impulse(gravity_up * 16.0);
+            external_impulse.apply_impulse(gravity_up * JUMP_IMPULSE);
         }
     }
 }
@@ -144,29 +140,40 @@ pub struct FrictionSystemSet;
 const FRICTION_FACTOR: f32 = 0.4;
 
 pub fn apply_friction(
-    mut players: Query<(&mut LinearVelocity, &ShapeHits, &GravityBound), With<Player>>,
-    sensors_query: Query<&Sensor>,
+    mut players: Query<(&Position, &mut LinearVelocity, &Grounded, &GravityBound), With<Player>>,
+    ground_query: Query<(&Position, &LinearVelocity, &AngularVelocity), Without<Player>>,
 ) {
     // We need to apply friction to the player when they're on the ground
-    for (mut linear_velocity, shape_hits, gravity_bound) in &mut players {
-        let touching_ground = shape_hits
-            .iter()
-            .any(|hit| sensors_query.get(hit.entity).is_err());
-
-        if !touching_ground {
+    for (position, mut linear_velocity, grounded, gravity_bound) in &mut players {
+        let Some(ground) = grounded.ground else {
             continue;
-        }
+        };
+
+        // Friction works relative to the ground so moving and spinning planets
+        // carry the player along
+        let ground_velocity = ground_query.get(ground).map_or(
+            Vec3::ZERO,
+            |(ground_position, ground_linear_velocity, ground_angular_velocity)| {
+                surface_velocity(
+                    ground_linear_velocity.0,
+                    ground_angular_velocity.0,
+                    ground_position.0,
+                    position.0,
+                )
+            },
+        );
+        let relative_velocity = linear_velocity.0 - ground_velocity;
 
         let gravity_force = gravity_bound.gravity_force;
         let gravity_up = -gravity_force.normalize();
         // project the velocity onto the ground plane and apply friction to it
-        let ground_velocity = linear_velocity.0.dot(gravity_up) * gravity_up;
-        let mut tangential_velocity = linear_velocity.0 - ground_velocity;
+        let vertical_velocity = relative_velocity.dot(gravity_up) * gravity_up;
+        let mut tangential_velocity = relative_velocity - vertical_velocity;
 
         // apply friction to the tangential velocity
         tangential_velocity *= 1.0 - FRICTION_FACTOR;
 
         // combine the velocities
-        linear_velocity.0 = ground_velocity + tangential_velocity;
+        linear_velocity.0 = ground_velocity + vertical_velocity + tangential_velocity;
     }
 }
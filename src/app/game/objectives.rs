@@ -0,0 +1,388 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::{
+    app::game_levels::{CurrentLevel, Level, ObjectiveDescriptor},
+    assets::levels::LevelCollection,
+};
+
+use super::{
+    black_hole::SwallowedEvent,
+    game_state_machine::{GameState, GameTransitionEvent},
+    junk::{Junk, JunkCollectedEvent, JunkCollisionEvent, PushedByPlayer},
+    player::Player,
+    stats::LevelStats,
+    InGameLevelMarker,
+};
+
+pub struct ObjectivesPlugin;
+
+impl Plugin for ObjectivesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Objectives>()
+            .add_event::<ObjectiveCompletedEvent>()
+            .add_systems(
+                OnTransition {
+                    from: GameState::AssetLoading,
+                    to: GameState::Playing,
+                },
+                setup_objectives,
+            )
+            .add_systems(
+                Update,
+                (
+                    (
+                        track_collected_junk,
+                        track_delivered_junk,
+                        track_fed_junk,
+                        track_beacons,
+                        track_survival,
+                    ),
+                    check_objectives,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// The objectives of the level currently being played and how far along each
+/// of them is.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct Objectives {
+    pub objectives: Vec<Objective>,
+
+    pub time_limit: Option<f32>,
+
+    /// Set once the level has been completed or failed so that's only sent
+    /// once
+    pub ended: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Objective {
+    pub descriptor: ObjectiveDescriptor,
+
+    pub state: ObjectiveState,
+
+    /// Whether [`ObjectiveCompletedEvent`] has been sent for this objective
+    pub reported: bool,
+}
+
+#[derive(Debug, Default, Clone)]
+pub enum ObjectiveState {
+    #[default]
+    InProgress,
+
+    /// Counting up towards the number the objective asks for
+    Counting(u32),
+
+    /// Delivered junk is remembered so it's only counted once
+    Delivered(HashSet<Entity>),
+
+    Completed,
+}
+
+impl Objective {
+    pub fn is_completed(&self) -> bool {
+        matches!(self.state, ObjectiveState::Completed)
+    }
+}
+
+/// Sent once when an objective is completed.
+#[derive(Event, Clone)]
+pub struct ObjectiveCompletedEvent {
+    /// The index of the objective in [`Objectives::objectives`]
+    pub index: usize,
+}
+
+/// Marks where the player has to get to for [`ObjectiveDescriptor::ReachBeacon`]
+#[derive(Component)]
+pub struct Beacon {
+    pub objective: usize,
+
+    pub radius: f32,
+}
+
+fn setup_objectives(
+    mut commands: Commands,
+    current_level: Res<CurrentLevel>,
+    level_collection: Res<LevelCollection>,
+    levels: Res<Assets<Level>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(level) = level_collection
+        .levels
+        .get(current_level.0)
+        .and_then(|handle| levels.get(handle))
+    else {
+        commands.insert_resource(Objectives::default());
+        return;
+    };
+
+    for (index, objective) in level.objectives.iter().enumerate() {
+        if let ObjectiveDescriptor::ReachBeacon { position, radius } = objective {
+            commands.spawn((
+                PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::UVSphere {
+                        radius: 0.5,
+                        ..default()
+                    })),
+                    material: materials.add(StandardMaterial {
+                        emissive: Color::rgb(0.2, 0.8, 1.0),
+                        ..default()
+                    }),
+                    transform: Transform::from_translation(*position),
+                    ..default()
+                },
+                Beacon {
+                    objective: index,
+                    radius: *radius,
+                },
+                InGameLevelMarker,
+            ));
+        }
+    }
+
+    commands.insert_resource(Objectives {
+        objectives: level
+            .objectives
+            .iter()
+            .cloned()
+            .map(|descriptor| Objective {
+                state: match descriptor {
+                    ObjectiveDescriptor::CollectJunk { .. }
+                    | ObjectiveDescriptor::FeedBlackHole { .. } => ObjectiveState::Counting(0),
+                    ObjectiveDescriptor::DeliverJunk { .. } => {
+                        ObjectiveState::Delivered(HashSet::new())
+                    }
+                    _ => ObjectiveState::InProgress,
+                },
+                descriptor,
+                reported: false,
+            })
+            .collect(),
+        time_limit: level.time_limit,
+        ended: false,
+    });
+}
+
+fn track_collected_junk(
+    mut objectives: ResMut<Objectives>,
+    mut junk_collected_reader: EventReader<JunkCollectedEvent>,
+) {
+    let collected = junk_collected_reader.iter().count() as u32;
+
+    if collected == 0 {
+        return;
+    }
+
+    for objective in &mut objectives.objectives {
+        let ObjectiveDescriptor::CollectJunk { count } = objective.descriptor else {
+            continue;
+        };
+
+        if let ObjectiveState::Counting(progress) = &mut objective.state {
+            *progress += collected;
+
+            if *progress >= count {
+                objective.state = ObjectiveState::Completed;
+            }
+        }
+    }
+}
+
+/// Only junk the player has pushed counts, so junk that starts out on the
+/// planet or drifts onto it by itself isn't delivered.
+fn track_delivered_junk(
+    mut objectives: ResMut<Objectives>,
+    mut junk_collision_reader: EventReader<JunkCollisionEvent>,
+    junk_query: Query<&Junk, With<PushedByPlayer>>,
+    name_query: Query<&Name>,
+) {
+    for junk_collision in junk_collision_reader.iter() {
+        let Ok(Junk { collectable: false }) = junk_query.get(junk_collision.junk) else {
+            continue;
+        };
+
+        let Ok(planet_name) = name_query.get(junk_collision.planet) else {
+            continue;
+        };
+
+        for objective in &mut objectives.objectives {
+            let ObjectiveDescriptor::DeliverJunk { planet, count } = &objective.descriptor else {
+                continue;
+            };
+
+            if planet.as_str() != planet_name.as_str() {
+                continue;
+            }
+
+            if let ObjectiveState::Delivered(delivered) = &mut objective.state {
+                delivered.insert(junk_collision.junk);
+
+                if delivered.len() as u32 >= *count {
+                    objective.state = ObjectiveState::Completed;
+                }
+            }
+        }
+    }
+}
+
+fn track_fed_junk(
+    mut objectives: ResMut<Objectives>,
+    mut swallowed_reader: EventReader<SwallowedEvent>,
+) {
+    let fed = swallowed_reader
+        .iter()
+        .filter(|swallowed| swallowed.junk)
+        .count() as u32;
+
+    if fed == 0 {
+        return;
+    }
+
+    for objective in &mut objectives.objectives {
+        let ObjectiveDescriptor::FeedBlackHole { count } = objective.descriptor else {
+            continue;
+        };
+
+        if let ObjectiveState::Counting(progress) = &mut objective.state {
+            *progress += fed;
+
+            if *progress >= count {
+                objective.state = ObjectiveState::Completed;
+            }
+        }
+    }
+}
+
+fn track_beacons(
+    mut objectives: ResMut<Objectives>,
+    beacon_query: Query<(&Beacon, &GlobalTransform)>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+) {
+    for player_transform in &player_query {
+        for (beacon, beacon_transform) in &beacon_query {
+            let distance = player_transform
+                .translation()
+                .distance(beacon_transform.translation());
+
+            if distance <= beacon.radius {
+                if let Some(objective) = objectives.objectives.get_mut(beacon.objective) {
+                    objective.state = ObjectiveState::Completed;
+                }
+            }
+        }
+    }
+}
+
+fn track_survival(mut objectives: ResMut<Objectives>, stats: Res<LevelStats>) {
+    let elapsed = stats.time.elapsed_secs();
+
+    for objective in &mut objectives.objectives {
+        if let ObjectiveDescriptor::Survive { seconds } = objective.descriptor {
+            if elapsed >= seconds {
+                objective.state = ObjectiveState::Completed;
+            }
+        }
+    }
+}
+
+/// Completes the level once every objective is done, or fails it once it's
+/// out of time. Either is only sent once per level.
+fn check_objectives(
+    mut objectives: ResMut<Objectives>,
+    stats: Res<LevelStats>,
+    mut objective_completed_writer: EventWriter<ObjectiveCompletedEvent>,
+    mut transition_writer: EventWriter<GameTransitionEvent>,
+) {
+    if objectives.ended {
+        return;
+    }
+
+    for (index, objective) in objectives.objectives.iter_mut().enumerate() {
+        if objective.is_completed() && !objective.reported {
+            objective.reported = true;
+            objective_completed_writer.send(ObjectiveCompletedEvent { index });
+        }
+    }
+
+    let all_completed = !objectives.objectives.is_empty()
+        && objectives.objectives.iter().all(Objective::is_completed);
+
+    if all_completed {
+        transition_writer.send(GameTransitionEvent::Complete);
+        objectives.ended = true;
+    } else if objectives
+        .time_limit
+        .is_some_and(|time_limit| stats.time.elapsed_secs() > time_limit)
+    {
+        transition_writer.send(GameTransitionEvent::Fail);
+        objectives.ended = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Objectives>()
+            .init_resource::<LevelStats>()
+            .add_event::<ObjectiveCompletedEvent>()
+            .add_event::<GameTransitionEvent>()
+            .add_systems(Update, check_objectives);
+
+        app
+    }
+
+    /// A level with a single objective that's already done, inserted the same
+    /// way [`setup_objectives`] does it
+    fn start_level(app: &mut App) {
+        app.world.insert_resource(Objectives {
+            objectives: vec![Objective {
+                descriptor: ObjectiveDescriptor::Survive { seconds: 0.0 },
+                state: ObjectiveState::Completed,
+                reported: false,
+            }],
+            time_limit: None,
+            ended: false,
+        });
+    }
+
+    fn sent<E: Event + Clone>(app: &App) -> Vec<E> {
+        let events = app.world.resource::<Events<E>>();
+
+        events.get_reader().iter(events).cloned().collect()
+    }
+
+    #[test]
+    fn levels_back_to_back_both_complete() {
+        let mut app = app();
+
+        for _ in 0..2 {
+            start_level(&mut app);
+
+            app.update();
+            app.update();
+
+            assert_eq!(
+                sent::<GameTransitionEvent>(&app),
+                vec![GameTransitionEvent::Complete]
+            );
+            assert_eq!(
+                sent::<ObjectiveCompletedEvent>(&app)
+                    .iter()
+                    .map(|completed| completed.index)
+                    .collect::<Vec<_>>(),
+                vec![0]
+            );
+
+            // Let the events of this level expire before the next one
+            app.update();
+            app.update();
+        }
+    }
+}
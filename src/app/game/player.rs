@@ -3,10 +3,11 @@ use bevy::prelude::*;
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
-    fn build(&self, _app: &mut App) {
-        // app;
+    fn build(&self, app: &mut App) {
+        app.register_type::<Player>();
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 pub struct Player;
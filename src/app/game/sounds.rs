@@ -2,7 +2,9 @@ use std::time::Duration;
 
 use bevy::prelude::*;
 
-use super::{game_state_machine::GameState, junk::JunkCollisionEvent};
+use crate::assets::sounds::SoundCollection;
+
+use super::{black_hole::SwallowedEvent, game_state_machine::GameState, junk::JunkCollisionEvent};
 
 pub struct SoundsPlugin;
 
@@ -14,7 +16,7 @@ impl Plugin for SoundsPlugin {
         )))
         .add_systems(
             Update,
-            (junk_collisions,).run_if(in_state(GameState::Playing)),
+            (junk_collisions, black_hole_swallows).run_if(in_state(GameState::Playing)),
         );
     }
 }
@@ -55,3 +57,19 @@ fn junk_collisions(
         sound_collision_stopwatch.0.reset();
     }
 }
+
+fn black_hole_swallows(
+    mut commands: Commands,
+    sound_collection: Res<SoundCollection>,
+    mut swallowed_reader: EventReader<SwallowedEvent>,
+) {
+    // Several bodies swallowed at once only need the one sound
+    if swallowed_reader.iter().count() == 0 {
+        return;
+    }
+
+    commands.spawn(AudioBundle {
+        source: sound_collection.blackhole.clone(),
+        settings: PlaybackSettings::DESPAWN,
+    });
+}
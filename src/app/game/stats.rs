@@ -0,0 +1,74 @@
+use bevy::{prelude::*, time::Stopwatch};
+
+use crate::{
+    app::game_levels::{CurrentLevel, Level},
+    assets::levels::LevelCollection,
+};
+
+use super::{game_state_machine::GameState, junk::JunkCollectedEvent};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelStats>()
+            .add_systems(
+                OnTransition {
+                    from: GameState::AssetLoading,
+                    to: GameState::Playing,
+                },
+                reset_stats,
+            )
+            .add_systems(
+                Update,
+                (tick_level_time, count_collected_junk).run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Statistics about the level currently being played, shown on the result
+/// screens.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct LevelStats {
+    /// Time spent playing, not counting time paused
+    pub time: Stopwatch,
+
+    pub junk_collected: u32,
+
+    /// How much collectable junk there was at the start of the level
+    pub junk_total: u32,
+
+    /// How many times the player got lost in space
+    pub deaths: u32,
+}
+
+fn reset_stats(
+    mut commands: Commands,
+    current_level: Res<CurrentLevel>,
+    level_collection: Res<LevelCollection>,
+    levels: Res<Assets<Level>>,
+) {
+    let junk_total = level_collection
+        .levels
+        .get(current_level.0)
+        .and_then(|handle| levels.get(handle))
+        .map_or(0, |level| {
+            level.junk.iter().filter(|junk| junk.collectable).count() as u32
+        });
+
+    commands.insert_resource(LevelStats {
+        junk_total,
+        ..default()
+    });
+}
+
+fn tick_level_time(mut stats: ResMut<LevelStats>, time: Res<Time>) {
+    stats.time.tick(time.delta());
+}
+
+fn count_collected_junk(
+    mut stats: ResMut<LevelStats>,
+    mut junk_collected_reader: EventReader<JunkCollectedEvent>,
+) {
+    stats.junk_collected += junk_collected_reader.iter().count() as u32;
+}
@@ -0,0 +1,363 @@
+//! Predicts where bodies will go under gravity and shows the player where a
+//! jump will take them.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_xpbd_3d::{parry::math::Isometry, prelude::*};
+
+use super::{
+    game_state_machine::GameState,
+    gravity::{
+        blended_force, dominant_contribution, Contribution, GravityBlending, GravityBound,
+        GravityDisabled, GravityPriority, GravitySource,
+    },
+    grounded::Grounded,
+    movement::JUMP_IMPULSE,
+    player::Player,
+};
+
+pub struct TrajectoryPlugin;
+
+impl Plugin for TrajectoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrajectoryPreview>().add_systems(
+            Update,
+            preview_jump.run_if(in_state(GameState::Playing).and_then(preview_enabled)),
+        );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct TrajectoryPreview {
+    pub enabled: bool,
+
+    /// How far ahead the jump arc is predicted
+    pub seconds: f32,
+}
+
+impl Default for TrajectoryPreview {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            seconds: 3.0,
+        }
+    }
+}
+
+/// Answers what gravity is like anywhere in the level rather than only for
+/// bodies inside of sensors, so where things will go can be predicted.
+#[derive(SystemParam)]
+pub struct GravityField<'w, 's> {
+    source_query: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static dyn GravitySource,
+            &'static Position,
+            &'static Rotation,
+            &'static Collider,
+            Option<&'static GravityPriority>,
+        ),
+        (With<Sensor>, Without<GravityDisabled>),
+    >,
+
+    sensor_query: Query<'w, 's, Entity, With<Sensor>>,
+
+    spatial_query: SpatialQuery<'w, 's>,
+}
+
+/// How far ahead and how finely to predict a trajectory.
+#[derive(Debug, Clone)]
+pub struct TrajectorySettings {
+    pub seconds: f32,
+
+    pub time_step: f32,
+
+    /// Entities the trajectory passes through, usually the body itself
+    pub ignored: Vec<Entity>,
+}
+
+impl Default for TrajectorySettings {
+    fn default() -> Self {
+        Self {
+            seconds: 2.0,
+            time_step: 1.0 / 30.0,
+            ignored: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Trajectory {
+    pub points: Vec<Vec3>,
+
+    /// What the trajectory ends on, if it hits anything. The last point is
+    /// where it's hit.
+    pub hit: Option<Entity>,
+}
+
+impl<'w, 's> GravityField<'w, 's> {
+    fn contributions(&self, point: Vec3, mass: f32) -> Vec<Contribution> {
+        self.source_query
+            .iter()
+            .filter_map(
+                |(source, gravity_sources, position, rotation, collider, priority)| {
+                    let local_point = rotation.0.inverse() * (point - position.0);
+                    let inside = collider
+                        .get_shape()
+                        .contains_point(&Isometry::identity(), &local_point.into());
+
+                    if !inside {
+                        return None;
+                    }
+
+                    let force = gravity_sources
+                        .iter()
+                        .map(|gravity_source| {
+                            gravity_source.calculate_force(position.0, rotation.0, point, mass)
+                        })
+                        .sum();
+
+                    Some(Contribution {
+                        source,
+                        force,
+                        priority: priority.copied().unwrap_or_default().0,
+                    })
+                },
+            )
+            .collect()
+    }
+
+    /// The gravity a body with the given mass and blending would feel at
+    /// `point`. There's no memory of earlier sources so there's no hysteresis
+    /// and crossfades act like
+    /// [`BlendMode::Strongest`](super::gravity::BlendMode::Strongest).
+    pub fn force_at(&self, point: Vec3, mass: f32, blending: &GravityBlending) -> Vec3 {
+        let contributions = self.contributions(point, mass);
+        let dominant = dominant_contribution(&contributions, None, blending);
+
+        blended_force(&contributions, dominant, blending.mode)
+    }
+
+    /// Simulates a body only acted on by gravity until it hits something or
+    /// runs out of time.
+    pub fn predict_trajectory(
+        &self,
+        position: Vec3,
+        velocity: Vec3,
+        mass: f32,
+        blending: &GravityBlending,
+        settings: &TrajectorySettings,
+    ) -> Trajectory {
+        let query_filter = SpatialQueryFilter::new().without_entities(
+            settings
+                .ignored
+                .iter()
+                .copied()
+                .chain(self.sensor_query.iter()),
+        );
+
+        simulate(
+            position,
+            velocity,
+            settings,
+            |point| self.force_at(point, mass, blending) / mass,
+            |origin, direction, max_distance| {
+                self.spatial_query
+                    .cast_ray(origin, direction, max_distance, true, query_filter.clone())
+                    .map(|hit| (hit.entity, hit.time_of_impact))
+            },
+        )
+    }
+}
+
+/// Steps a body through `acceleration` until it runs out of time or `cast`
+/// reports it hitting something. `cast` is given the start, direction and
+/// length of each step and returns what's hit and how far along the step.
+fn simulate(
+    position: Vec3,
+    velocity: Vec3,
+    settings: &TrajectorySettings,
+    acceleration: impl Fn(Vec3) -> Vec3,
+    cast: impl Fn(Vec3, Vec3, f32) -> Option<(Entity, f32)>,
+) -> Trajectory {
+    let mut trajectory = Trajectory {
+        points: vec![position],
+        hit: None,
+    };
+
+    let (mut position, mut velocity) = (position, velocity);
+    let steps = (settings.seconds / settings.time_step).ceil() as usize;
+
+    for _ in 0..steps {
+        velocity += acceleration(position) * settings.time_step;
+
+        let step = velocity * settings.time_step;
+        let distance = step.length();
+        if distance <= f32::EPSILON {
+            continue;
+        }
+
+        if let Some((entity, time_of_impact)) = cast(position, step / distance, distance) {
+            trajectory
+                .points
+                .push(position + step / distance * time_of_impact);
+            trajectory.hit = Some(entity);
+            break;
+        }
+
+        position += step;
+        trajectory.points.push(position);
+    }
+
+    trajectory
+}
+
+fn preview_enabled(preview: Res<TrajectoryPreview>) -> bool {
+    preview.enabled
+}
+
+/// Draws the arc the player would follow if they jumped right now.
+fn preview_jump(
+    preview: Res<TrajectoryPreview>,
+    gravity_field: GravityField,
+    mut gizmos: Gizmos,
+    player_query: Query<
+        (
+            Entity,
+            &Position,
+            &LinearVelocity,
+            &Mass,
+            &Grounded,
+            &GravityBound,
+            Option<&GravityBlending>,
+        ),
+        With<Player>,
+    >,
+) {
+    for (entity, position, linear_velocity, mass, grounded, gravity_bound, blending) in
+        &player_query
+    {
+        if !grounded.grounded || mass.0 <= 0.0 {
+            continue;
+        }
+
+        let settings = TrajectorySettings {
+            seconds: preview.seconds,
+            ignored: vec![entity],
+            ..default()
+        };
+
+        let blending = blending.cloned().unwrap_or_default();
+        let jump_velocity = linear_velocity.0 + gravity_bound.up * JUMP_IMPULSE / mass.0;
+        let trajectory = gravity_field.predict_trajectory(
+            position.0,
+            jump_velocity,
+            mass.0,
+            &blending,
+            &settings,
+        );
+
+        gizmos.linestrip(trajectory.points.iter().copied(), Color::WHITE);
+
+        if let (Some(_), Some(&landing)) = (trajectory.hit, trajectory.points.last()) {
+            let up = -gravity_field
+                .force_at(landing, mass.0, &blending)
+                .normalize_or_zero();
+            gizmos.circle(landing, up, 0.5, Color::WHITE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::game::gravity::{GravityFalloff, PointGravity};
+
+    use super::*;
+
+    fn nothing_hit(_: Vec3, _: Vec3, _: f32) -> Option<(Entity, f32)> {
+        None
+    }
+
+    #[test]
+    fn steps_under_point_gravity() {
+        // Pulls with 2 * 5 = 10 at the inner radius of 5 so a quarter of that
+        // twice as far out
+        let gravity = PointGravity {
+            gravity_strength: 2.0,
+            center_mass: 5.0,
+            falloff: GravityFalloff::inverse_square(5.0),
+        };
+        let mass = 3.0;
+        let settings = TrajectorySettings {
+            seconds: 0.1,
+            time_step: 0.1,
+            ..default()
+        };
+
+        let trajectory = simulate(
+            Vec3::Y * 10.0,
+            Vec3::X,
+            &settings,
+            |point| gravity.calculate_force(Vec3::ZERO, Quat::IDENTITY, point, mass) / mass,
+            nothing_hit,
+        );
+
+        // v = (1, 0, 0) + (0, -2.5, 0) * 0.1
+        // p = (0, 10, 0) + v * 0.1
+        assert_eq!(trajectory.points.len(), 2);
+        assert!(trajectory.points[1].abs_diff_eq(Vec3::new(0.1, 9.975, 0.0), 1e-5));
+        assert_eq!(trajectory.hit, None);
+    }
+
+    #[test]
+    fn stops_at_the_horizon() {
+        let settings = TrajectorySettings {
+            seconds: 1.0,
+            time_step: 0.25,
+            ..default()
+        };
+
+        let trajectory = simulate(Vec3::ZERO, Vec3::X, &settings, |_| Vec3::ZERO, nothing_hit);
+
+        assert_eq!(
+            trajectory.points,
+            vec![
+                Vec3::ZERO,
+                Vec3::X * 0.25,
+                Vec3::X * 0.5,
+                Vec3::X * 0.75,
+                Vec3::X,
+            ]
+        );
+        assert_eq!(trajectory.hit, None);
+    }
+
+    #[test]
+    fn stops_where_it_hits() {
+        let wall = Entity::from_raw(7);
+        let settings = TrajectorySettings {
+            seconds: 1.0,
+            time_step: 0.25,
+            ..default()
+        };
+
+        // A wall across the x axis at 0.6
+        let trajectory = simulate(
+            Vec3::ZERO,
+            Vec3::X,
+            &settings,
+            |_| Vec3::ZERO,
+            |origin, direction, max_distance| {
+                let distance = (0.6 - origin.x) / direction.x;
+                (0.0..=max_distance)
+                    .contains(&distance)
+                    .then_some((wall, distance))
+            },
+        );
+
+        assert_eq!(trajectory.points.len(), 4);
+        assert!(trajectory.points[3].abs_diff_eq(Vec3::X * 0.6, 1e-5));
+        assert_eq!(trajectory.hit, Some(wall));
+    }
+}
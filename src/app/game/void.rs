@@ -0,0 +1,302 @@
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::{AngularVelocity, LinearVelocity, Position, Rotation};
+
+use crate::{
+    app::game_levels::{CurrentLevel, Level, VoidDescriptor},
+    assets::levels::LevelCollection,
+};
+
+use super::{
+    game_state_machine::{GameState, GameTransitionEvent},
+    gravity::GravityBound,
+    player::Player,
+    stats::LevelStats,
+    InGameLevelMarker,
+};
+
+pub struct VoidPlugin;
+
+impl Plugin for VoidPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Drifting>()
+            .init_resource::<VoidRules>()
+            .init_resource::<RespawnPoint>()
+            .add_event::<PlayerLostEvent>()
+            .add_systems(
+                OnTransition {
+                    from: GameState::AssetLoading,
+                    to: GameState::Playing,
+                },
+                setup_void,
+            )
+            .add_systems(
+                Update,
+                (
+                    activate_checkpoints,
+                    detect_lost_players,
+                    respawn_lost_players,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing))
+                    .in_set(VoidSystemSet),
+            );
+    }
+}
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct VoidSystemSet;
+
+/// How the current level deals with players drifting off into space.
+#[derive(Resource, Default, Debug)]
+pub struct VoidRules {
+    pub descriptor: VoidDescriptor,
+
+    /// Set once the level has been failed so that's only sent once
+    pub failed: bool,
+}
+
+/// Where the player respawns after getting lost in space.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct RespawnPoint(pub Vec3);
+
+/// How long a body has been floating outside of every gravity field.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+pub struct Drifting {
+    pub seconds: f32,
+}
+
+#[derive(Component, Debug)]
+pub struct Checkpoint {
+    pub radius: f32,
+
+    pub activated: bool,
+}
+
+impl Checkpoint {
+    /// Activated checkpoints light up
+    pub fn emissive(&self) -> Color {
+        if self.activated {
+            Color::rgb(0.2, 1.0, 0.4)
+        } else {
+            Color::BLACK
+        }
+    }
+}
+
+/// Sent when the player is lost in space, either from drifting for too long or
+/// leaving the level's bounds.
+#[derive(Event)]
+pub struct PlayerLostEvent {
+    pub player: Entity,
+}
+
+fn setup_void(
+    mut commands: Commands,
+    current_level: Res<CurrentLevel>,
+    level_collection: Res<LevelCollection>,
+    levels: Res<Assets<Level>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(level) = level_collection
+        .levels
+        .get(current_level.0)
+        .and_then(|handle| levels.get(handle))
+    else {
+        return;
+    };
+
+    commands.insert_resource(VoidRules {
+        descriptor: level.void.clone(),
+        failed: false,
+    });
+    commands.insert_resource(RespawnPoint(level.player_spawn));
+
+    for checkpoint in &level.checkpoints {
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Cylinder {
+                    radius: 0.1,
+                    height: 1.5,
+                    ..default()
+                })),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::GRAY,
+                    ..default()
+                }),
+                transform: Transform::from_translation(checkpoint.position),
+                ..default()
+            },
+            Checkpoint {
+                radius: checkpoint.radius,
+                activated: false,
+            },
+            InGameLevelMarker,
+        ));
+    }
+}
+
+fn activate_checkpoints(
+    mut respawn_point: ResMut<RespawnPoint>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut checkpoint_query: Query<(&mut Checkpoint, &GlobalTransform, &Handle<StandardMaterial>)>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+) {
+    for player_transform in &player_query {
+        for (mut checkpoint, checkpoint_transform, material) in &mut checkpoint_query {
+            let checkpoint_position = checkpoint_transform.translation();
+
+            if checkpoint.activated
+                || player_transform.translation().distance(checkpoint_position) > checkpoint.radius
+            {
+                continue;
+            }
+
+            checkpoint.activated = true;
+            respawn_point.0 = checkpoint_position;
+
+            if let Some(material) = materials.get_mut(material) {
+                material.emissive = checkpoint.emissive();
+            }
+        }
+    }
+}
+
+fn detect_lost_players(
+    time: Res<Time>,
+    void_rules: Res<VoidRules>,
+    mut player_query: Query<(Entity, &Position, &GravityBound, &mut Drifting), With<Player>>,
+    mut player_lost_writer: EventWriter<PlayerLostEvent>,
+) {
+    for (player, position, gravity_bound, mut drifting) in &mut player_query {
+        if gravity_bound.gravity_force == Vec3::ZERO {
+            drifting.seconds += time.delta_seconds();
+        } else {
+            drifting.seconds = 0.0;
+        }
+
+        let out_of_bounds = void_rules
+            .descriptor
+            .bounds
+            .is_some_and(|(min, max)| position.0.cmplt(min).any() || position.0.cmpgt(max).any());
+
+        if out_of_bounds || drifting.seconds > void_rules.descriptor.lost_in_space_seconds {
+            drifting.seconds = 0.0;
+            player_lost_writer.send(PlayerLostEvent { player });
+        }
+    }
+}
+
+/// Without respawning the level is failed, once.
+fn respawn_lost_players(
+    mut void_rules: ResMut<VoidRules>,
+    respawn_point: Res<RespawnPoint>,
+    mut stats: ResMut<LevelStats>,
+    mut player_lost_reader: EventReader<PlayerLostEvent>,
+    mut player_query: Query<
+        (
+            &mut Position,
+            &mut Rotation,
+            &mut LinearVelocity,
+            &mut AngularVelocity,
+        ),
+        With<Player>,
+    >,
+    mut transition_writer: EventWriter<GameTransitionEvent>,
+) {
+    for player_lost in player_lost_reader.iter() {
+        if void_rules.failed {
+            break;
+        }
+
+        stats.deaths += 1;
+
+        if !void_rules.descriptor.respawn {
+            transition_writer.send(GameTransitionEvent::Fail);
+            void_rules.failed = true;
+            continue;
+        }
+
+        let Ok((mut position, mut rotation, mut linear_velocity, mut angular_velocity)) =
+            player_query.get_mut(player_lost.player)
+        else {
+            continue;
+        };
+
+        position.0 = respawn_point.0;
+        *rotation = Rotation::default();
+        linear_velocity.0 = Vec3::ZERO;
+        angular_velocity.0 = Vec3::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<VoidRules>()
+            .init_resource::<RespawnPoint>()
+            .init_resource::<LevelStats>()
+            .add_event::<PlayerLostEvent>()
+            .add_event::<GameTransitionEvent>()
+            .add_systems(Update, respawn_lost_players);
+
+        app
+    }
+
+    /// A level where getting lost fails it, inserted the same way
+    /// [`setup_void`] does it
+    fn start_level(app: &mut App) -> Entity {
+        app.world.insert_resource(VoidRules {
+            descriptor: VoidDescriptor {
+                respawn: false,
+                ..default()
+            },
+            failed: false,
+        });
+
+        app.world
+            .spawn((
+                Player,
+                Position::default(),
+                Rotation::default(),
+                LinearVelocity::default(),
+                AngularVelocity::default(),
+            ))
+            .id()
+    }
+
+    fn fails(app: &App) -> usize {
+        let events = app.world.resource::<Events<GameTransitionEvent>>();
+
+        events
+            .get_reader()
+            .iter(events)
+            .filter(|transition| **transition == GameTransitionEvent::Fail)
+            .count()
+    }
+
+    #[test]
+    fn levels_back_to_back_both_fail() {
+        let mut app = app();
+
+        for _ in 0..2 {
+            let player = start_level(&mut app);
+
+            app.world.send_event(PlayerLostEvent { player });
+            app.world.send_event(PlayerLostEvent { player });
+            app.update();
+            app.world.send_event(PlayerLostEvent { player });
+            app.update();
+
+            assert_eq!(fails(&app), 1);
+
+            // Let the events of this level expire before the next one
+            app.update();
+            app.update();
+        }
+    }
+}
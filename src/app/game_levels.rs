@@ -1,3 +1,525 @@
-// TODO: To be replaced with ron files :)
-// An array of levels
-pub const LEVELS: [&str; 3] = ["Level 1", "Level 2", "Level 3"];
+//! Levels are described in RON files under `assets/levels` and loaded through
+//! the asset server, so new levels can be added without touching any code.
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::assets::{environment::PlanetType, items::ItemType, levels::LevelCollection};
+
+use super::{
+    app_state_machine::AppState,
+    game::{
+        gravity::{FalloffKind, GravityCurve, GravityFalloff},
+        gravity_switch::{Activation, SwitchAction},
+    },
+};
+
+pub struct GameLevelsPlugin;
+
+impl Plugin for GameLevelsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Level>()
+            .init_asset_loader::<LevelLoader>()
+            .init_resource::<CurrentLevel>()
+            .add_systems(OnExit(AppState::AssetLoading), sort_levels);
+    }
+}
+
+/// The index into [`LevelCollection::levels`] of the level being played. Outside
+/// of a level this is the last level that was played.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrentLevel(pub usize);
+
+/// A single playable level.
+#[derive(Debug, Deserialize, TypeUuid, TypePath)]
+#[uuid = "5c3a4a0e-2f7e-4a4b-9d0b-6f1f3c8a2e71"]
+pub struct Level {
+    /// The name shown on the level selection screen
+    pub name: String,
+
+    /// Where the player is spawned in world space
+    pub player_spawn: Vec3,
+
+    #[serde(default)]
+    pub platforms: Vec<PlatformDescriptor>,
+
+    #[serde(default)]
+    pub planets: Vec<PlanetDescriptor>,
+
+    #[serde(default)]
+    pub tubes: Vec<TubeDescriptor>,
+
+    /// Wind, vortices, drag and repulsors
+    #[serde(default)]
+    pub fields: Vec<FieldDescriptor>,
+
+    #[serde(default)]
+    pub black_holes: Vec<BlackHoleDescriptor>,
+
+    /// Triggers and switches that change gravity
+    #[serde(default)]
+    pub switches: Vec<SwitchDescriptor>,
+
+    #[serde(default)]
+    pub junk: Vec<JunkDescriptor>,
+
+    /// The level is completed once every objective is. A level without
+    /// objectives can't be completed.
+    #[serde(default)]
+    pub objectives: Vec<ObjectiveDescriptor>,
+
+    /// The level is failed if it isn't completed within this many seconds
+    #[serde(default)]
+    pub time_limit: Option<f32>,
+
+    /// What happens when the player drifts off into space
+    #[serde(default)]
+    pub void: VoidDescriptor,
+
+    /// Places the player respawns at once they've touched them
+    #[serde(default)]
+    pub checkpoints: Vec<CheckpointDescriptor>,
+
+    /// Content that's unlocked by completing this level
+    #[serde(default)]
+    pub unlocks: Vec<String>,
+}
+
+/// A flat, square surface with a uniform gravity field above it.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PlatformDescriptor {
+    /// Used by switches to refer to this platform
+    #[serde(default)]
+    pub name: Option<String>,
+
+    pub position: Vec3,
+
+    /// The length of each side of the surface
+    pub size: f32,
+
+    pub mass: f32,
+
+    pub gravity: GravityDescriptor,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PlanetDescriptor {
+    /// Used by objectives and switches to refer to this planet
+    #[serde(default)]
+    pub name: Option<String>,
+
+    pub planet_type: PlanetType,
+
+    pub position: Vec3,
+
+    pub mass: f32,
+
+    #[serde(default)]
+    pub friction: FrictionDescriptor,
+
+    pub gravity: GravityDescriptor,
+
+    #[serde(default)]
+    pub motion: Option<MotionDescriptor>,
+
+    /// How fast the planet spins around its center, in radians per second
+    /// around each axis
+    #[serde(default)]
+    pub angular_velocity: Vec3,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum MotionDescriptor {
+    /// Circles around `center`, or around the planet with the given name,
+    /// keeping the distance the planet starts at
+    Orbit {
+        #[serde(default)]
+        center: Vec3,
+        #[serde(default)]
+        around: Option<String>,
+        #[serde(default = "MotionDescriptor::default_axis")]
+        axis: Vec3,
+        /// Seconds per revolution
+        period: f32,
+    },
+
+    /// Moves between the points at `speed` units per second, either looping
+    /// back to the first point or turning around at the last one
+    Path {
+        points: Vec<Vec3>,
+        speed: f32,
+        #[serde(default)]
+        looping: bool,
+    },
+}
+
+impl MotionDescriptor {
+    fn default_axis() -> Vec3 {
+        Vec3::Y
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FrictionDescriptor {
+    pub dynamic_coefficient: f32,
+
+    pub static_coefficient: f32,
+}
+
+impl Default for FrictionDescriptor {
+    fn default() -> Self {
+        Self {
+            dynamic_coefficient: 0.4,
+            static_coefficient: 0.8,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GravityDescriptor {
+    pub kind: GravityKind,
+
+    /// The radius of the sensor that bodies need to be inside of to feel this
+    /// gravity. For planar gravity this is half the side of the sensor box.
+    pub radius: f32,
+
+    pub strength: f32,
+
+    /// How the strength changes with distance, up to the edge of the sensor.
+    /// Planar gravity measures the height above the plane. Defaults to
+    /// inverse-square for point gravity and constant otherwise.
+    #[serde(default)]
+    pub falloff: Option<FalloffDescriptor>,
+
+    /// Wins over sources with a lower priority for bodies that blend their
+    /// gravity by priority
+    #[serde(default)]
+    pub priority: i32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FalloffDescriptor {
+    pub kind: FalloffKind,
+
+    #[serde(default = "FalloffDescriptor::default_inner_radius")]
+    pub inner_radius: f32,
+
+    /// How far inside the edge of the sensor gravity starts fading out
+    #[serde(default)]
+    pub fade: f32,
+}
+
+impl FalloffDescriptor {
+    fn default_inner_radius() -> f32 {
+        1.0
+    }
+
+    /// The falloff for a sensor reaching `outer_radius` from the source.
+    pub fn to_falloff(&self, outer_radius: f32) -> GravityFalloff {
+        if matches!(self.kind, FalloffKind::InverseSquare) && self.inner_radius <= 0.0 {
+            warn!(
+                "Inverse-square falloff needs an inner radius above zero, not {}, so it's constant",
+                self.inner_radius
+            );
+        }
+
+        GravityFalloff {
+            kind: self.kind.clone(),
+            inner_radius: self.inner_radius,
+            outer_radius,
+            fade: self.fade,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum GravityKind {
+    /// Pulls towards the center of the body
+    Point,
+
+    /// Pulls uniformly against the given normal
+    Planar { normal: Vec3 },
+
+    /// Pulls towards the closest point on the body's surface. A distance field
+    /// with cells of `sdf_cell_size` is precomputed if given.
+    Surface {
+        #[serde(default)]
+        sdf_cell_size: Option<f32>,
+    },
+}
+
+/// A solid tube along a curve with gravity pulling towards its center line.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TubeDescriptor {
+    pub position: Vec3,
+
+    /// Relative to `position`
+    pub curve: GravityCurve,
+
+    /// The radius of the solid tube
+    #[serde(default = "TubeDescriptor::default_thickness")]
+    pub thickness: f32,
+
+    /// How far from the center line bodies feel the tube's gravity
+    pub gravity_radius: f32,
+
+    pub gravity_strength: f32,
+
+    #[serde(default)]
+    pub gravity_falloff: Option<FalloffDescriptor>,
+}
+
+impl TubeDescriptor {
+    fn default_thickness() -> f32 {
+        0.5
+    }
+}
+
+/// A point of gravity that swallows whatever gets too close.
+#[derive(Debug, Deserialize, Clone)]
+pub struct BlackHoleDescriptor {
+    pub position: Vec3,
+
+    pub mass: f32,
+
+    /// How far from the center bodies feel the black hole's gravity
+    pub gravity_radius: f32,
+
+    pub gravity_strength: f32,
+
+    /// Bodies closer than this to the center are swallowed
+    #[serde(default = "BlackHoleDescriptor::default_event_horizon")]
+    pub event_horizon: f32,
+
+    /// Bodies closer than this to the center start getting stretched
+    #[serde(default = "BlackHoleDescriptor::default_stretch_radius")]
+    pub stretch_radius: f32,
+
+    /// Swallowed junk comes out here instead of disappearing
+    #[serde(default)]
+    pub teleport_to: Option<Vec3>,
+}
+
+impl BlackHoleDescriptor {
+    fn default_event_horizon() -> f32 {
+        1.0
+    }
+
+    fn default_stretch_radius() -> f32 {
+        6.0
+    }
+}
+
+/// Changes the gravity of named planets and platforms.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SwitchDescriptor {
+    pub position: Vec3,
+
+    pub shape: FieldShape,
+
+    #[serde(default)]
+    pub activation: Activation,
+
+    pub action: SwitchAction,
+
+    /// The names of the planets and platforms whose gravity is switched
+    pub targets: Vec<String>,
+
+    /// How long planar gravity takes to turn
+    #[serde(default = "SwitchDescriptor::default_seconds")]
+    pub seconds: f32,
+}
+
+impl SwitchDescriptor {
+    fn default_seconds() -> f32 {
+        1.0
+    }
+}
+
+/// A force field that isn't gravity.
+#[derive(Debug, Deserialize, Clone)]
+pub struct FieldDescriptor {
+    pub position: Vec3,
+
+    pub shape: FieldShape,
+
+    pub kind: FieldKind,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum FieldShape {
+    Sphere { radius: f32 },
+
+    Box { size: Vec3 },
+}
+
+impl FieldShape {
+    /// How far the field reaches from its center
+    pub fn extent(&self) -> f32 {
+        match self {
+            FieldShape::Sphere { radius } => *radius,
+            FieldShape::Box { size } => size.length() / 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum FieldKind {
+    Wind {
+        direction: Vec3,
+        strength: f32,
+    },
+
+    Vortex {
+        axis: Vec3,
+        strength: f32,
+        #[serde(default)]
+        pull: f32,
+    },
+
+    Drag {
+        coefficient: f32,
+    },
+
+    Repulsor {
+        strength: f32,
+        #[serde(default)]
+        falloff: Option<FalloffDescriptor>,
+    },
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct JunkDescriptor {
+    pub item_type: ItemType,
+
+    pub position: Vec3,
+
+    #[serde(default = "JunkDescriptor::default_mass")]
+    pub mass: f32,
+
+    #[serde(default = "JunkDescriptor::default_radius")]
+    pub radius: f32,
+
+    /// Whether the player picks this up by touching it
+    #[serde(default = "JunkDescriptor::default_collectable")]
+    pub collectable: bool,
+}
+
+impl JunkDescriptor {
+    fn default_mass() -> f32 {
+        1.0
+    }
+
+    fn default_radius() -> f32 {
+        0.3
+    }
+
+    fn default_collectable() -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct VoidDescriptor {
+    /// How long the player can be outside of every gravity field before
+    /// they're considered lost in space
+    pub lost_in_space_seconds: f32,
+
+    /// The player is lost as soon as they leave this box, given as its
+    /// `(min, max)` corners
+    pub bounds: Option<(Vec3, Vec3)>,
+
+    /// Whether a lost player respawns at the last checkpoint or fails the
+    /// level
+    pub respawn: bool,
+}
+
+impl Default for VoidDescriptor {
+    fn default() -> Self {
+        Self {
+            lost_in_space_seconds: 4.0,
+            bounds: None,
+            respawn: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CheckpointDescriptor {
+    pub position: Vec3,
+
+    /// How close the player has to get to activate it
+    #[serde(default = "CheckpointDescriptor::default_radius")]
+    pub radius: f32,
+}
+
+impl CheckpointDescriptor {
+    fn default_radius() -> f32 {
+        1.5
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum ObjectiveDescriptor {
+    /// Pick up this many pieces of junk
+    CollectJunk { count: u32 },
+
+    /// Get within `radius` of a beacon placed at `position`
+    ReachBeacon { position: Vec3, radius: f32 },
+
+    /// Don't fail the level for this many seconds
+    Survive { seconds: f32 },
+
+    /// Push this many pieces of junk that aren't collectable onto the planet
+    /// with the given name
+    DeliverJunk { planet: String, count: u32 },
+
+    /// Get black holes to swallow this many pieces of junk
+    FeedBlackHole { count: u32 },
+}
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let level = ron::de::from_bytes::<Level>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(level));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+/// Folders aren't loaded in any particular order so we sort the levels by their
+/// file name. This lets designers order levels by prefixing them, e.g. `01_`.
+pub fn sort_levels(mut level_collection: ResMut<LevelCollection>, asset_server: Res<AssetServer>) {
+    level_collection.levels.sort_by_key(|handle| {
+        asset_server
+            .get_handle_path(handle)
+            .map(|asset_path| asset_path.path().to_path_buf())
+    });
+}
+
+/// Identifies a level by its path so that saved progress doesn't depend on the
+/// order of the levels.
+pub fn level_key(asset_server: &AssetServer, handle: &Handle<Level>) -> Option<String> {
+    asset_server
+        .get_handle_path(handle)
+        .map(|asset_path| asset_path.path().to_string_lossy().into_owned())
+}
@@ -0,0 +1,237 @@
+use bevy::prelude::*;
+
+use crate::{
+    assets::{fonts::FontCollection, levels::LevelCollection, sounds::SoundCollection},
+    utility::despawn_components,
+};
+
+use super::{
+    app_state_machine::AppTransitionEvent,
+    game::{game_state_machine::GameState, stats::LevelStats},
+    game_levels::CurrentLevel,
+    theme::{change_button_colors, NORMAL_BUTTON, TEXT_COLOR},
+    AppState,
+};
+
+pub struct LevelResultsPlugin;
+
+impl Plugin for LevelResultsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Completed), setup_completed)
+            .add_systems(OnEnter(GameState::Failed), setup_failed)
+            .add_systems(
+                Update,
+                (change_button_colors, level_results_actions)
+                    .run_if(in_state(GameState::Completed).or_else(in_state(GameState::Failed))),
+            )
+            .add_systems(
+                OnExit(AppState::InGameLevel),
+                despawn_components::<LevelResultsMarker>,
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct LevelResultsMarker;
+
+// All actions that can be triggered from a button click
+#[derive(Component, Debug, Copy, Clone)]
+enum ResultsButtonAction {
+    NextLevel(usize),
+    Retry,
+    QuitToMenu,
+}
+
+impl ResultsButtonAction {
+    fn text(&self) -> &str {
+        match self {
+            ResultsButtonAction::NextLevel(_) => "Next Level",
+            ResultsButtonAction::Retry => "Retry",
+            ResultsButtonAction::QuitToMenu => "Menu",
+        }
+    }
+}
+
+fn setup_completed(
+    mut commands: Commands,
+    font_collection: Res<FontCollection>,
+    sound_collection: Res<SoundCollection>,
+    level_collection: Res<LevelCollection>,
+    current_level: Res<CurrentLevel>,
+    stats: Res<LevelStats>,
+) {
+    commands.spawn(AudioBundle {
+        source: sound_collection.win_loud.clone(),
+        settings: PlaybackSettings::DESPAWN,
+    });
+
+    let next_level = current_level.0 + 1;
+    let mut actions = Vec::new();
+
+    if next_level < level_collection.levels.len() {
+        actions.push(ResultsButtonAction::NextLevel(next_level));
+    }
+
+    actions.extend([ResultsButtonAction::Retry, ResultsButtonAction::QuitToMenu]);
+
+    spawn_results(
+        &mut commands,
+        &font_collection,
+        "Level Complete",
+        &stats,
+        &actions,
+    );
+}
+
+fn setup_failed(
+    mut commands: Commands,
+    font_collection: Res<FontCollection>,
+    sound_collection: Res<SoundCollection>,
+    stats: Res<LevelStats>,
+) {
+    commands.spawn(AudioBundle {
+        source: sound_collection.gameover_loud.clone(),
+        settings: PlaybackSettings::DESPAWN,
+    });
+
+    spawn_results(
+        &mut commands,
+        &font_collection,
+        "Level Failed",
+        &stats,
+        &[ResultsButtonAction::Retry, ResultsButtonAction::QuitToMenu],
+    );
+}
+
+fn spawn_results(
+    commands: &mut Commands,
+    font_collection: &FontCollection,
+    title: &str,
+    stats: &LevelStats,
+    actions: &[ResultsButtonAction],
+) {
+    let button_style = Style {
+        width: Val::Px(260.0),
+        height: Val::Px(64.0),
+        margin: UiRect::all(Val::Px(16.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    let button_text_style = TextStyle {
+        font_size: 40.0,
+        color: TEXT_COLOR,
+        font: font_collection.comfortaa_bold.clone(),
+    };
+
+    let stat_text_style = TextStyle {
+        font_size: 32.0,
+        color: TEXT_COLOR,
+        font: font_collection.comfortaa_regular.clone(),
+    };
+
+    let elapsed = stats.time.elapsed_secs();
+    let stat_lines = [
+        format!("Time: {}:{:04.1}", (elapsed / 60.0) as u32, elapsed % 60.0),
+        format!("Junk: {} / {}", stats.junk_collected, stats.junk_total),
+        format!("Lost in space: {}", stats.deaths),
+    ];
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                // Dim the game behind the results
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..default()
+            },
+            LevelResultsMarker,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    title,
+                    TextStyle {
+                        font: font_collection.comfortaa_bold.clone(),
+                        font_size: 80.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                // Because this is a distinct label widget and
+                // not button/list item text, this is necessary
+                // for accessibility to treat the text accordingly.
+                Label,
+            ));
+
+            for line in stat_lines {
+                parent.spawn((
+                    TextBundle::from_section(line, stat_text_style.clone()),
+                    Label,
+                ));
+            }
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        margin: UiRect::top(Val::Px(24.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for action in actions {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: button_style.clone(),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                *action,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    action.text(),
+                                    button_text_style.clone(),
+                                ));
+                            });
+                    }
+                });
+        });
+}
+
+fn level_results_actions(
+    mut interaction_query: Query<
+        (&Interaction, &ResultsButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut transition_writer: EventWriter<AppTransitionEvent>,
+) {
+    for (interaction, action) in &mut interaction_query {
+        // check if interaction is clicked
+        if *interaction != Interaction::Pressed {
+            continue;
+        };
+
+        match action {
+            ResultsButtonAction::NextLevel(level) => {
+                transition_writer.send(AppTransitionEvent::NextLevel(*level));
+            }
+            ResultsButtonAction::Retry => {
+                transition_writer.send(AppTransitionEvent::Retry);
+            }
+            ResultsButtonAction::QuitToMenu => {
+                transition_writer.send(AppTransitionEvent::QuitToMenu);
+            }
+        }
+    }
+}
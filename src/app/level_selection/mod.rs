@@ -1,13 +1,14 @@
 use bevy::prelude::*;
 
 use crate::{
-    assets::{backgrounds::BackgroundCollection, fonts::FontCollection},
+    assets::{backgrounds::BackgroundCollection, fonts::FontCollection, levels::LevelCollection},
+    saving::PlayerProgress,
     utility::despawn_components,
 };
 
 use super::{
     app_state_machine::AppTransitionEvent,
-    game_levels::LEVELS,
+    game_levels::Level,
     navigation::BackButton,
     theme::{change_button_colors, NORMAL_BUTTON},
     AppState,
@@ -40,6 +41,10 @@ fn setup(
     mut commands: Commands,
     background_collection: Res<BackgroundCollection>,
     font_collection: Res<FontCollection>,
+    level_collection: Res<LevelCollection>,
+    levels: Res<Assets<Level>>,
+    progress: Res<PlayerProgress>,
+    asset_server: Res<AssetServer>,
 ) {
     commands.spawn((Camera2dBundle::default(), LevelSelectionMarker));
 
@@ -101,35 +106,48 @@ fn setup(
                     ..default()
                 },))
                 .with_children(|parent| {
-                    LEVELS.into_iter().enumerate().for_each(|(index, _)| {
-                        parent
-                            .spawn((
-                                ButtonBundle {
-                                    style: Style {
-                                        width: Val::Px(160.0),
-                                        height: Val::Px(64.0),
-                                        margin: UiRect::all(Val::Px(16.0)),
-                                        justify_content: JustifyContent::Center,
-                                        align_items: AlignItems::Center,
-                                        align_self: AlignSelf::Center,
-                                        ..default()
+                    for (index, handle) in level_collection.levels.iter().enumerate() {
+                        let level_name = levels
+                            .get(handle)
+                            .map(|level| level.name.clone())
+                            .unwrap_or_else(|| format!("Level {}", index + 1));
+                        let unlocked =
+                            progress.is_level_unlocked(&asset_server, &level_collection, index);
+
+                        let mut button = parent.spawn(ButtonBundle {
+                            style: Style {
+                                width: Val::Px(260.0),
+                                height: Val::Px(64.0),
+                                margin: UiRect::all(Val::Px(16.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                align_self: AlignSelf::Center,
+                                ..default()
+                            },
+                            background_color: NORMAL_BUTTON.into(),
+                            ..default()
+                        });
+
+                        // Locked levels are shown but can't be selected
+                        if unlocked {
+                            button.insert(SelectLevelButton(index));
+                        }
+
+                        button.with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                level_name,
+                                TextStyle {
+                                    font: font_collection.comfortaa_bold.clone(),
+                                    font_size: 40.0,
+                                    color: if unlocked {
+                                        Color::rgb(0.9, 0.9, 0.9)
+                                    } else {
+                                        Color::rgb(0.5, 0.5, 0.5)
                                     },
-                                    background_color: NORMAL_BUTTON.into(),
-                                    ..default()
                                 },
-                                SelectLevelButton(index),
-                            ))
-                            .with_children(|parent| {
-                                parent.spawn(TextBundle::from_section(
-                                    format!("Level {}", index + 1),
-                                    TextStyle {
-                                        font: font_collection.comfortaa_bold.clone(),
-                                        font_size: 40.0,
-                                        color: Color::rgb(0.9, 0.9, 0.9),
-                                    },
-                                ));
-                            });
-                    });
+                            ));
+                        });
+                    }
                 });
         });
 }
@@ -4,40 +4,48 @@ use bevy_asset_loader::prelude::LoadingStateAppExt;
 
 use crate::assets::{
     backgrounds::BackgroundCollection, fonts::FontCollection, images::ImageCollection,
-    music::MusicCollection, sounds::SoundCollection, ui_sounds::UiSoundCollection,
+    levels::LevelCollection, music::MusicCollection, sounds::SoundCollection,
+    ui_sounds::UiSoundCollection,
 };
 
 use self::{
     app_state_machine::{AppState, AppStateMachinePlugin},
     game::GamePlugin,
+    game_levels::GameLevelsPlugin,
+    level_results::LevelResultsPlugin,
     level_selection::LevelSelectionPlugin,
     main_menu::MainMenuPlugin,
     navigation::NavigationPlugin,
+    pause_menu::PauseMenuPlugin,
     player_input::PlayerInputPlugin,
     settings_dialog::SettingsDialogPlugin,
 };
 
-mod app_state_machine;
-mod game;
-mod game_levels;
+pub mod app_state_machine;
+pub mod game;
+pub mod game_levels;
+mod level_results;
 mod level_selection;
 mod main_menu;
 mod navigation;
-mod player_input;
+mod pause_menu;
+pub mod player_input;
 mod settings_dialog;
 mod theme;
+mod transition_table;
 
 pub struct AppPlugin;
 
 impl Plugin for AppPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(AppStateMachinePlugin)
+        app.add_plugins((AppStateMachinePlugin, GameLevelsPlugin))
             .add_collection_to_loading_state::<_, MusicCollection>(AppState::AssetLoading)
             .add_collection_to_loading_state::<_, UiSoundCollection>(AppState::AssetLoading)
             .add_collection_to_loading_state::<_, SoundCollection>(AppState::AssetLoading)
             .add_collection_to_loading_state::<_, BackgroundCollection>(AppState::AssetLoading)
             .add_collection_to_loading_state::<_, ImageCollection>(AppState::AssetLoading)
             .add_collection_to_loading_state::<_, FontCollection>(AppState::AssetLoading)
+            .add_collection_to_loading_state::<_, LevelCollection>(AppState::AssetLoading)
             .add_plugins((
                 NavigationPlugin,
                 PlayerInputPlugin,
@@ -45,6 +53,8 @@ impl Plugin for AppPlugin {
                 GamePlugin,
                 LevelSelectionPlugin,
                 SettingsDialogPlugin,
+                PauseMenuPlugin,
+                LevelResultsPlugin,
             ));
     }
 }
@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::{assets::fonts::FontCollection, utility::despawn_components};
+
+use super::{
+    app_state_machine::AppTransitionEvent,
+    game::game_state_machine::{GameState, GameTransitionEvent},
+    player_input::{GameAction, UiAction},
+    theme::{change_button_colors, NORMAL_BUTTON, TEXT_COLOR},
+    AppState,
+};
+
+pub struct PauseMenuPlugin;
+
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Paused), setup)
+            // Coming back from the settings we're still paused
+            .add_systems(
+                OnEnter(AppState::InGameLevel),
+                setup.run_if(in_state(GameState::Paused)),
+            )
+            .add_systems(
+                Update,
+                (
+                    toggle_pause,
+                    (change_button_colors, pause_menu_actions).run_if(in_state(GameState::Paused)),
+                )
+                    .run_if(in_state(AppState::InGameLevel)),
+            )
+            .add_systems(
+                OnExit(GameState::Paused),
+                despawn_components::<PauseMenuMarker>,
+            )
+            .add_systems(
+                OnExit(AppState::InGameLevel),
+                despawn_components::<PauseMenuMarker>,
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct PauseMenuMarker;
+
+// All actions that can be triggered from a button click
+#[derive(Component, Debug, Copy, Clone, EnumIter)]
+enum PauseButtonAction {
+    Resume,
+    Retry,
+    Settings,
+    QuitToMenu,
+}
+
+impl PauseButtonAction {
+    fn text(&self) -> &str {
+        match self {
+            PauseButtonAction::Resume => "Resume",
+            PauseButtonAction::Retry => "Retry",
+            PauseButtonAction::Settings => "Settings",
+            PauseButtonAction::QuitToMenu => "Quit to Menu",
+        }
+    }
+}
+
+fn setup(mut commands: Commands, font_collection: Res<FontCollection>) {
+    let button_style = Style {
+        width: Val::Px(260.0),
+        height: Val::Px(64.0),
+        margin: UiRect::all(Val::Px(16.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    let button_text_style = TextStyle {
+        font_size: 40.0,
+        color: TEXT_COLOR,
+        font: font_collection.comfortaa_bold.clone(),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                // Dim the game behind the menu
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..default()
+            },
+            PauseMenuMarker,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "Paused",
+                    TextStyle {
+                        font: font_collection.comfortaa_bold.clone(),
+                        font_size: 80.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                // Because this is a distinct label widget and
+                // not button/list item text, this is necessary
+                // for accessibility to treat the text accordingly.
+                Label,
+            ));
+
+            for action in PauseButtonAction::iter() {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: button_style.clone(),
+                            background_color: NORMAL_BUTTON.into(),
+                            ..default()
+                        },
+                        action,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            action.text(),
+                            button_text_style.clone(),
+                        ));
+                    });
+            }
+        });
+}
+
+fn toggle_pause(
+    action_state_query: Query<&ActionState<UiAction>>,
+    game_action_state_query: Query<&ActionState<GameAction>>,
+    current_state: Res<State<GameState>>,
+    mut transition_writer: EventWriter<GameTransitionEvent>,
+) {
+    // Both are bound to the same keys by default, so this only pauses once
+    let pause_pressed = action_state_query
+        .iter()
+        .any(|action_state| action_state.just_pressed(UiAction::Pause))
+        || game_action_state_query
+            .iter()
+            .any(|action_state| action_state.just_pressed(GameAction::Pause));
+
+    if !pause_pressed {
+        return;
+    }
+
+    match current_state.get() {
+        GameState::Playing => transition_writer.send(GameTransitionEvent::Pause),
+        GameState::Paused => transition_writer.send(GameTransitionEvent::Unpause),
+        _ => {}
+    }
+}
+
+fn pause_menu_actions(
+    mut interaction_query: Query<
+        (&Interaction, &PauseButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut game_transition_writer: EventWriter<GameTransitionEvent>,
+    mut app_transition_writer: EventWriter<AppTransitionEvent>,
+) {
+    for (interaction, action) in &mut interaction_query {
+        // check if interaction is clicked
+        if *interaction != Interaction::Pressed {
+            continue;
+        };
+
+        match action {
+            PauseButtonAction::Resume => {
+                game_transition_writer.send(GameTransitionEvent::Unpause);
+            }
+            PauseButtonAction::Retry => {
+                app_transition_writer.send(AppTransitionEvent::Retry);
+            }
+            PauseButtonAction::Settings => {
+                app_transition_writer.send(AppTransitionEvent::Settings);
+            }
+            PauseButtonAction::QuitToMenu => {
+                app_transition_writer.send(AppTransitionEvent::QuitToMenu);
+            }
+        }
+    }
+}
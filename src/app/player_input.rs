@@ -4,16 +4,17 @@ use bevy::reflect::TypePath;
 use leafwing_input_manager::orientation::Direction;
 use leafwing_input_manager::prelude::*;
 
-use super::AppState;
-
 // This plugin maps inputs to an input-type agnostic action-state
 // We need to provide it with an enum which stores the possible actions a player could take
 pub struct PlayerInputPlugin;
 
 impl Plugin for PlayerInputPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(InputManagerPlugin::<UiAction>::default())
-            .add_systems(OnEnter(AppState::InGameLevel), (setup_player,));
+        app.add_plugins((
+            InputManagerPlugin::<UiAction>::default(),
+            InputManagerPlugin::<GameAction>::default(),
+        ))
+        .add_systems(Startup, (setup_player,));
     }
 }
 
@@ -64,6 +65,14 @@ pub enum UiAction {
     Move(Direction),
 
     Select,
+
+    Pause,
+
+    QuickSave,
+
+    QuickLoad,
+
+    ToggleGravityOverlay,
 }
 
 impl UiAction {
@@ -83,6 +92,83 @@ impl UiAction {
         input_map.insert(KeyCode::Right, Move(Direction::EAST));
         input_map.insert(GamepadButtonType::DPadRight, Move(Direction::EAST));
 
+        input_map.insert(KeyCode::Escape, Pause);
+        input_map.insert(GamepadButtonType::Start, Pause);
+
+        input_map.insert(KeyCode::F5, QuickSave);
+        input_map.insert(KeyCode::F9, QuickLoad);
+
+        input_map.insert(KeyCode::F3, ToggleGravityOverlay);
+
+        input_map
+    }
+}
+
+/// What the astronaut can do while playing a level.
+#[derive(Actionlike, PartialEq, Clone, Copy, Debug, TypePath)]
+pub enum GameAction {
+    /// Dual-axis, how far the stick is pushed sets how fast the astronaut moves
+    Move,
+
+    Jump,
+
+    Interact,
+
+    Pause,
+
+    /// Dual-axis, turns the camera around the astronaut
+    Camera,
+}
+
+impl GameAction {
+    pub fn default_input_map() -> InputMap<Self> {
+        use GameAction::*;
+        let mut input_map = InputMap::default();
+
+        input_map.insert(VirtualDPad::arrow_keys(), Move);
+        input_map.insert(VirtualDPad::wasd(), Move);
+        input_map.insert(DualAxis::left_stick(), Move);
+
+        input_map.insert(KeyCode::Space, Jump);
+        input_map.insert(GamepadButtonType::South, Jump);
+
+        input_map.insert(KeyCode::E, Interact);
+        input_map.insert(GamepadButtonType::West, Interact);
+
+        input_map.insert(KeyCode::Escape, Pause);
+        input_map.insert(GamepadButtonType::Start, Pause);
+
+        input_map.insert(
+            VirtualDPad {
+                up: KeyCode::I.into(),
+                down: KeyCode::K.into(),
+                left: KeyCode::J.into(),
+                right: KeyCode::L.into(),
+            },
+            Camera,
+        );
+        input_map.insert(DualAxis::right_stick(), Camera);
+
         input_map
     }
 }
+
+/// How far a dual-axis action is pushed, no further than `1.0` in any
+/// direction so diagonals on a keyboard aren't faster.
+pub fn action_axis(action_state: &ActionState<GameAction>, action: GameAction) -> Vec2 {
+    action_state
+        .axis_pair(action)
+        .map_or(Vec2::ZERO, |axis_pair| axis_pair.xy())
+        .clamp_length_max(1.0)
+}
+
+/// Run condition that's true when any player just pressed the given action.
+pub fn ui_action_just_pressed(
+    action: UiAction,
+) -> impl FnMut(Query<&ActionState<UiAction>>) -> bool {
+    move |action_state_query: Query<&ActionState<UiAction>>| {
+        action_state_query
+            .iter()
+            .any(|action_state| action_state.just_pressed(action))
+    }
+}
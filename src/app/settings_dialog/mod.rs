@@ -19,8 +19,16 @@ impl Plugin for SettingsDialogPlugin {
 #[derive(Component)]
 pub struct SettingsDialogMarker;
 
-fn setup(mut commands: Commands, font_collection: Res<FontCollection>) {
-    commands.spawn((Camera2dBundle::default(), SettingsDialogMarker));
+fn setup(
+    mut commands: Commands,
+    font_collection: Res<FontCollection>,
+    camera_query: Query<(), With<Camera>>,
+) {
+    // When opened from the pause menu the game's camera is still around and
+    // renders the UI on top of the paused level
+    if camera_query.is_empty() {
+        commands.spawn((Camera2dBundle::default(), SettingsDialogMarker));
+    }
 
     commands
         .spawn((
@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+
+/// The legal edges of a state machine expressed as data. Each edge is a
+/// `(from, kind, to)` triple where `kind` identifies the transition event.
+pub struct TransitionTable<S: 'static, K: 'static> {
+    edges: &'static [(S, K, S)],
+}
+
+impl<S: States, K: PartialEq> TransitionTable<S, K> {
+    pub const fn new(edges: &'static [(S, K, S)]) -> Self {
+        Self { edges }
+    }
+
+    /// Returns the state we'd end up in by taking the `kind` transition from
+    /// `from`, or `None` if that transition isn't legal.
+    pub fn target(&self, from: &S, kind: &K) -> Option<&S> {
+        self.edges
+            .iter()
+            .find(|(edge_from, edge_kind, _)| edge_from == from && edge_kind == kind)
+            .map(|(_, _, to)| to)
+    }
+
+    /// Lets tests walk every edge of the table.
+    #[cfg(test)]
+    pub fn edges(&self) -> &'static [(S, K, S)] {
+        self.edges
+    }
+}
+
+/// Sent when a transition event was received that isn't legal from the
+/// current state. The transition is ignored.
+#[derive(Event, Debug, Clone)]
+pub struct InvalidTransition<S: States, K> {
+    pub from: S,
+
+    pub kind: K,
+}
@@ -4,6 +4,7 @@ use bevy::gltf::Gltf;
 use bevy::prelude::{Handle, Resource};
 use bevy::reflect::Reflect;
 use bevy_asset_loader::prelude::*;
+use serde::Deserialize;
 use strum_macros::EnumIter;
 
 #[derive(Debug, Copy, Clone, EnumIter)]
@@ -274,7 +275,7 @@ impl RockType {
     }
 }
 
-#[derive(Debug, Default, Copy, Clone, EnumIter, Reflect)]
+#[derive(Debug, Default, Copy, Clone, EnumIter, Reflect, Deserialize)]
 pub enum PlanetType {
     #[default]
     Planet1,
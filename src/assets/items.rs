@@ -5,9 +5,10 @@ use bevy::{
     scene::Scene,
 };
 use bevy_asset_loader::prelude::*;
+use serde::Deserialize;
 use strum_macros::EnumIter;
 
-#[derive(Debug, Copy, Clone, EnumIter)]
+#[derive(Debug, Copy, Clone, EnumIter, Deserialize)]
 pub enum ItemType {
     Bullets,
     Crate,
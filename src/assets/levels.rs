@@ -0,0 +1,11 @@
+use bevy::asset::AssetServer;
+use bevy::prelude::{Handle, Resource};
+use bevy_asset_loader::prelude::*;
+
+use crate::app::game_levels::Level;
+
+#[derive(AssetCollection, Resource)]
+pub struct LevelCollection {
+    #[asset(path = "levels", collection(typed))]
+    pub levels: Vec<Handle<Level>>,
+}
@@ -4,6 +4,7 @@ pub mod environment;
 pub mod fonts;
 pub mod images;
 pub mod items;
+pub mod levels;
 pub mod music;
 pub mod sounds;
 pub mod ui_sounds;
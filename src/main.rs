@@ -2,6 +2,7 @@ use std::time::Duration;
 
 use app::AppPlugin;
 use bevy::{asset::ChangeWatcher, prelude::*, window::PresentMode};
+use saving::SavingPlugin;
 
 mod app;
 mod assets;
@@ -36,6 +37,6 @@ fn main() {
                     ..default()
                 }),
         )
-        .add_plugins(AppPlugin)
+        .add_plugins((AppPlugin, SavingPlugin))
         .run();
 }
@@ -1,68 +1,565 @@
-// use std::{fs::File, io::Write};
-
-// use bevy::{prelude::*, scene::DynamicSceneBundle, tasks::IoTaskPool};
-// use bevy_xpbd_3d::prelude::{Friction, Mass, Position, RigidBody};
-
-// pub struct SavingPlugin;
-
-// impl Plugin for SavingPlugin {
-//     fn build(&self, app: &mut App) {
-//         app.register_type::<Planet>()
-//             .register_type::<Junk>()
-//             .register_type::<Transform>()
-//             .register_type::<Friction>()
-//             .register_type::<Mass>()
-//             .register_type::<Position>()
-//             .register_type::<RigidBody>()
-//             .add_systems(
-//                 (save_scene_system,).in_schedule(OnEnter(GameState::Playing)), // .run_if(input_toggle_active(false, KeyCode::Escape)),
-//             );
-//     }
-// }
-
-// const NEW_SCENE_FILE_PATH: &str = "scenes/scene_one.scn.ron";
-
-// fn load_scene_system(mut commands: Commands, asset_server: Res<AssetServer>) {
-//     // "Spawning" a scene bundle creates a new entity and spawns new instances
-//     // of the given scene's entities as children of that entity.
-//     commands.spawn(DynamicSceneBundle {
-//         // Scenes are loaded just like any other asset.
-//         scene: asset_server.load(NEW_SCENE_FILE_PATH),
-//         ..default()
-//     });
-// }
-
-// fn save_scene_system(world: &mut World) {
-//     // The TypeRegistry resource contains information about all registered types (including
-//     // components). This is used to construct scenes.
-//     let mut query = world.query_filtered::<Entity, With<Transform>>();
-//     let type_registry = world.resource::<AppTypeRegistry>();
-
-//     // Scenes can be created from any ECS World. You can either create a new one for the scene or
-//     // use the current World.
-//     let scene_world = World::new();
-//     let mut scene_builder = DynamicSceneBuilder::from_world(&scene_world);
-
-//     scene_builder.extract_entities(query.iter(&world));
-
-//     let scene = scene_builder.build();
-
-//     // Scenes can be serialized like this:
-//     let serialized_scene = scene.serialize_ron(type_registry).unwrap();
-
-//     // Showing the scene in the console
-//     info!("{}", serialized_scene);
-
-//     // Writing the scene to a new file. Using a task to avoid calling the filesystem APIs in a system
-//     // as they are blocking
-//     // This can't work in WASM as there is no filesystem access
-//     #[cfg(not(target_arch = "wasm32"))]
-//     IoTaskPool::get()
-//         .spawn(async move {
-//             // Write the scene RON data to file
-//             File::create(format!("assets/{NEW_SCENE_FILE_PATH}"))
-//                 .and_then(|mut file| file.write(serialized_scene.as_bytes()))
-//                 .expect("Error while writing scene to file");
-//         })
-//         .detach();
-// }
+//! Persists the player's progress between sessions and quick saves the level
+//! being played.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    ecs::{entity::EntityMap, reflect::ReflectMapEntities},
+    hierarchy::despawn_with_children_recursive,
+    prelude::*,
+    tasks::IoTaskPool,
+    utils::{HashMap, HashSet},
+};
+use bevy_xpbd_3d::prelude::{AngularVelocity, LinearVelocity, Position, Rotation};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{
+        app_state_machine::AppState,
+        game::{
+            black_hole::BlackHole,
+            force_fields::{Drag, Repulsor, Vortex, Wind},
+            game_state_machine::GameState,
+            gravity::{
+                CurvedGravity, GravityBlending, GravityBound, GravityDisabled, GravityPriority,
+                PlanarGravity, PointGravity, SurfaceGravity, Upright,
+            },
+            gravity_switch::{GravitySwitch, PlanarGravityTurn},
+            grounded::Grounded,
+            junk::{Junk, PushedByPlayer},
+            level::spawn_level,
+            motion::Motion,
+            objectives::{ObjectiveState, Objectives},
+            player::Player,
+            stats::LevelStats,
+            void::{Checkpoint, Drifting, RespawnPoint},
+            Planet,
+        },
+        game_levels::{level_key, sort_levels, CurrentLevel, Level},
+        player_input::{ui_action_just_pressed, UiAction},
+    },
+    assets::levels::LevelCollection,
+};
+
+const PROGRESS_FILE_NAME: &str = "progress.ron";
+
+pub struct SavingPlugin;
+
+impl Plugin for SavingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SaveId>()
+            .insert_resource(load_progress())
+            .init_resource::<ProgressWrites>()
+            .add_systems(
+                OnExit(AppState::AssetLoading),
+                restore_last_played_level.after(sort_levels),
+            )
+            .add_systems(
+                OnTransition {
+                    from: GameState::AssetLoading,
+                    to: GameState::Playing,
+                },
+                record_last_played_level,
+            )
+            .add_systems(OnEnter(GameState::Completed), record_completed_level)
+            // Loading the progress counts as a change, which doesn't need
+            // writing back
+            .add_systems(
+                Update,
+                save_progress.run_if(
+                    resource_changed::<PlayerProgress>()
+                        .and_then(not(resource_added::<PlayerProgress>())),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    quick_save.run_if(ui_action_just_pressed(UiAction::QuickSave)),
+                    quick_load.run_if(
+                        resource_exists::<QuickSave>()
+                            .and_then(ui_action_just_pressed(UiAction::QuickLoad)),
+                    ),
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnEnter(GameState::None), clear_quick_save);
+    }
+}
+
+/// Everything about the player's progress that's kept between sessions.
+/// Levels are referred to by their [`level_key`].
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct PlayerProgress {
+    pub completed_levels: BTreeSet<String>,
+
+    /// The fastest completion of each level in seconds
+    pub best_times: BTreeMap<String, f32>,
+
+    /// Content unlocked by completing levels
+    pub unlocked_content: BTreeSet<String>,
+
+    pub last_played_level: Option<String>,
+}
+
+impl PlayerProgress {
+    /// The first level is always unlocked, every other level is unlocked by
+    /// completing the one before it.
+    pub fn is_level_unlocked(
+        &self,
+        asset_server: &AssetServer,
+        level_collection: &LevelCollection,
+        index: usize,
+    ) -> bool {
+        let is_completed = |index: usize| {
+            level_collection
+                .levels
+                .get(index)
+                .and_then(|handle| level_key(asset_server, handle))
+                .is_some_and(|key| self.completed_levels.contains(&key))
+        };
+
+        index == 0 || is_completed(index) || is_completed(index - 1)
+    }
+}
+
+fn progress_path() -> Option<PathBuf> {
+    ProjectDirs::from("com", "stargazing-dino", "Cosmic Junkyard")
+        .map(|project_dirs| project_dirs.data_dir().join(PROGRESS_FILE_NAME))
+}
+
+fn load_progress() -> PlayerProgress {
+    let Some(path) = progress_path() else {
+        warn!("No data directory found, progress won't be saved");
+        return PlayerProgress::default();
+    };
+
+    let Ok(contents) = fs::read_to_string(&path) else {
+        // Most likely the first time the game is played
+        return PlayerProgress::default();
+    };
+
+    match ron::from_str::<PlayerProgress>(&contents) {
+        Ok(progress) => progress,
+        Err(error) => {
+            // Keep the corrupt file around so it isn't lost on the next save
+            let backup_path = path.with_extension("ron.bak");
+            warn!(
+                "Failed to read progress from {}, backing it up to {} and starting fresh: {}",
+                path.display(),
+                backup_path.display(),
+                error
+            );
+
+            if let Err(error) = fs::rename(&path, &backup_path) {
+                error!("Failed to back up corrupt progress: {}", error);
+            }
+
+            PlayerProgress::default()
+        }
+    }
+}
+
+/// Progress is written in the background so the writes can finish out of
+/// order. Each save gets the next generation and a write is dropped once a
+/// newer one has made it to disk.
+#[derive(Resource, Default)]
+struct ProgressWrites {
+    generation: u64,
+
+    /// The generation last written to disk, locked for the whole write so
+    /// only one happens at a time
+    written: Arc<Mutex<u64>>,
+}
+
+fn save_progress(progress: Res<PlayerProgress>, mut progress_writes: ResMut<ProgressWrites>) {
+    let Some(path) = progress_path() else {
+        return;
+    };
+
+    let contents = match ron::ser::to_string_pretty(&*progress, default()) {
+        Ok(contents) => contents,
+        Err(error) => {
+            error!("Failed to serialize progress: {}", error);
+            return;
+        }
+    };
+
+    progress_writes.generation += 1;
+    let generation = progress_writes.generation;
+    let written = progress_writes.written.clone();
+
+    // This can't work in WASM as there is no filesystem access
+    #[cfg(not(target_arch = "wasm32"))]
+    IoTaskPool::get()
+        .spawn(async move {
+            if let Err(error) = write_progress(&path, &contents, generation, &written) {
+                error!("Failed to write progress to {}: {}", path.display(), error);
+            }
+        })
+        .detach();
+}
+
+/// Writes to a temporary file first and moves it over the progress file, so a
+/// crash halfway through leaves the previous progress in place rather than a
+/// truncated file.
+fn write_progress(
+    path: &Path,
+    contents: &str,
+    generation: u64,
+    written: &Mutex<u64>,
+) -> io::Result<()> {
+    let mut written = written
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    if *written > generation {
+        return Ok(());
+    }
+
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+
+    let temporary_path = path.with_extension("ron.tmp");
+    let mut file = File::create(&temporary_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temporary_path, path)?;
+
+    *written = generation;
+
+    Ok(())
+}
+
+/// Points the current level at the last level played so "Continue" resumes it.
+fn restore_last_played_level(
+    progress: Res<PlayerProgress>,
+    asset_server: Res<AssetServer>,
+    level_collection: Res<LevelCollection>,
+    mut current_level: ResMut<CurrentLevel>,
+) {
+    let Some(last_played_level) = &progress.last_played_level else {
+        return;
+    };
+
+    if let Some(index) = level_collection
+        .levels
+        .iter()
+        .position(|handle| level_key(&asset_server, handle).as_ref() == Some(last_played_level))
+    {
+        current_level.0 = index;
+    }
+}
+
+fn record_last_played_level(
+    mut progress: ResMut<PlayerProgress>,
+    asset_server: Res<AssetServer>,
+    level_collection: Res<LevelCollection>,
+    current_level: Res<CurrentLevel>,
+) {
+    let key = level_collection
+        .levels
+        .get(current_level.0)
+        .and_then(|handle| level_key(&asset_server, handle));
+
+    if key.is_some() && progress.last_played_level != key {
+        progress.last_played_level = key;
+    }
+}
+
+fn record_completed_level(
+    mut progress: ResMut<PlayerProgress>,
+    asset_server: Res<AssetServer>,
+    level_collection: Res<LevelCollection>,
+    levels: Res<Assets<Level>>,
+    current_level: Res<CurrentLevel>,
+    stats: Res<LevelStats>,
+) {
+    let Some(handle) = level_collection.levels.get(current_level.0) else {
+        return;
+    };
+    let Some(key) = level_key(&asset_server, handle) else {
+        return;
+    };
+
+    let time = stats.time.elapsed_secs();
+    let best_time = progress.best_times.entry(key.clone()).or_insert(time);
+    *best_time = best_time.min(time);
+
+    progress.completed_levels.insert(key);
+
+    if let Some(level) = levels.get(handle) {
+        progress
+            .unlocked_content
+            .extend(level.unlocks.iter().cloned());
+    }
+}
+
+/// Identifies an entity spawned for a level. Levels are spawned the same way
+/// every time, so a quick save can be matched up with the level spawned again.
+#[derive(Component, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+pub struct SaveId(pub u32);
+
+impl SaveId {
+    /// Hands out this id and moves on to the next one.
+    pub fn next(&mut self) -> Self {
+        let id = *self;
+        self.0 += 1;
+        id
+    }
+}
+
+/// A snapshot of the level, taken by a quick save. Only the components listed
+/// in [`quick_save`] are kept, everything else comes from spawning the level
+/// again.
+#[derive(Resource)]
+pub struct QuickSave {
+    scene: DynamicScene,
+
+    /// Where to find each saved entity once the level is spawned again
+    save_ids: HashMap<Entity, SaveId>,
+
+    objectives: Objectives,
+
+    stats: LevelStats,
+
+    respawn_point: RespawnPoint,
+
+    /// Checkpoints aren't spawned with the level so they're kept as they are
+    /// and only have their activation put back
+    activated_checkpoints: HashMap<Entity, bool>,
+}
+
+fn quick_save(world: &mut World) {
+    let save_ids: HashMap<Entity, SaveId> = world
+        .query::<(Entity, &SaveId)>()
+        .iter(world)
+        .map(|(entity, save_id)| (entity, *save_id))
+        .collect();
+
+    let mut scene_builder = DynamicSceneBuilder::from_world(world);
+    scene_builder
+        .allow::<Position>()
+        .allow::<Rotation>()
+        .allow::<LinearVelocity>()
+        .allow::<AngularVelocity>()
+        .allow::<Planet>()
+        .allow::<Junk>()
+        .allow::<PushedByPlayer>()
+        .allow::<Player>()
+        .allow::<PointGravity>()
+        .allow::<PlanarGravity>()
+        .allow::<PlanarGravityTurn>()
+        .allow::<CurvedGravity>()
+        .allow::<SurfaceGravity>()
+        .allow::<GravityPriority>()
+        .allow::<GravityDisabled>()
+        .allow::<GravityBound>()
+        .allow::<GravityBlending>()
+        .allow::<BlackHole>()
+        .allow::<Wind>()
+        .allow::<Vortex>()
+        .allow::<Drag>()
+        .allow::<Repulsor>()
+        .allow::<GravitySwitch>()
+        .allow::<Upright>()
+        .allow::<Grounded>()
+        .allow::<Drifting>()
+        .allow::<Motion>();
+    scene_builder.extract_entities(save_ids.keys().copied());
+
+    let scene = scene_builder.build();
+    info!("Quick saved {} entities", scene.entities.len());
+
+    let activated_checkpoints = world
+        .query::<(Entity, &Checkpoint)>()
+        .iter(world)
+        .map(|(entity, checkpoint)| (entity, checkpoint.activated))
+        .collect();
+
+    let objectives = world.resource::<Objectives>().clone();
+    let stats = world.resource::<LevelStats>().clone();
+    let respawn_point = world.resource::<RespawnPoint>().clone();
+
+    world.insert_resource(QuickSave {
+        scene,
+        save_ids,
+        objectives,
+        stats,
+        respawn_point,
+        activated_checkpoints,
+    });
+}
+
+/// Spawns the level again and writes the quick saved components back onto it,
+/// so junk collected since the save comes back and anything that was already
+/// gone stays gone.
+fn quick_load(world: &mut World) {
+    world.resource_scope(|world, quick_save: Mut<QuickSave>| {
+        let level_entities: Vec<Entity> = world
+            .query_filtered::<Entity, With<SaveId>>()
+            .iter(world)
+            .collect();
+
+        for entity in level_entities {
+            // Children are already gone with their parents
+            if world.get_entity(entity).is_some() {
+                despawn_with_children_recursive(world, entity);
+            }
+        }
+
+        let mut spawn_level = IntoSystem::into_system(spawn_level);
+        spawn_level.initialize(world);
+        spawn_level.run((), world);
+        spawn_level.apply_deferred(world);
+
+        let respawned: HashMap<SaveId, Entity> = world
+            .query::<(Entity, &SaveId)>()
+            .iter(world)
+            .map(|(entity, save_id)| (*save_id, entity))
+            .collect();
+        let saved_ids: HashSet<SaveId> = quick_save.save_ids.values().copied().collect();
+
+        for (save_id, &entity) in &respawned {
+            if !saved_ids.contains(save_id) && world.get_entity(entity).is_some() {
+                despawn_with_children_recursive(world, entity);
+            }
+        }
+
+        let mut entity_map = EntityMap::default();
+        for (&saved_entity, save_id) in &quick_save.save_ids {
+            if let Some(&entity) = respawned.get(save_id) {
+                entity_map.insert(saved_entity, entity);
+            }
+        }
+
+        let type_registry = world.resource::<AppTypeRegistry>().clone();
+        let type_registry = type_registry.read();
+
+        // Which entities each type of component was written to, so the
+        // entities they refer to can be mapped afterwards
+        let mut written: HashMap<&str, Vec<Entity>> = HashMap::new();
+
+        for saved_entity in &quick_save.scene.entities {
+            let Some(entity) = entity_map.get(saved_entity.entity) else {
+                continue;
+            };
+            let mut entity_mut = world.entity_mut(entity);
+
+            for component in &saved_entity.components {
+                let Some(reflect_component) = type_registry
+                    .get_with_name(component.type_name())
+                    .and_then(|registration| registration.data::<ReflectComponent>())
+                else {
+                    warn!("{} isn't registered as a component", component.type_name());
+                    continue;
+                };
+
+                reflect_component.apply_or_insert(&mut entity_mut, &**component);
+                written
+                    .entry(component.type_name())
+                    .or_default()
+                    .push(entity);
+            }
+        }
+
+        for (type_name, entities) in &written {
+            if let Some(reflect_map_entities) = type_registry
+                .get_with_name(type_name)
+                .and_then(|registration| registration.data::<ReflectMapEntities>())
+            {
+                reflect_map_entities.map_entities(world, &mut entity_map, entities);
+            }
+        }
+
+        let mut objectives = quick_save.objectives.clone();
+        for objective in &mut objectives.objectives {
+            if let ObjectiveState::Delivered(delivered) = &mut objective.state {
+                *delivered = delivered
+                    .iter()
+                    .filter_map(|&junk| entity_map.get(junk))
+                    .collect();
+            }
+        }
+
+        // Replaced in place, along with which objectives were already
+        // reported, so objectives completed before the save aren't reported
+        // again and those completed after it are
+        *world.resource_mut::<Objectives>() = objectives;
+        *world.resource_mut::<LevelStats>() = quick_save.stats.clone();
+        *world.resource_mut::<RespawnPoint>() = quick_save.respawn_point.clone();
+
+        world.resource_scope(|world, mut materials: Mut<Assets<StandardMaterial>>| {
+            let mut checkpoint_query =
+                world.query::<(Entity, &mut Checkpoint, &Handle<StandardMaterial>)>();
+
+            for (entity, mut checkpoint, material) in checkpoint_query.iter_mut(world) {
+                let Some(&activated) = quick_save.activated_checkpoints.get(&entity) else {
+                    continue;
+                };
+
+                checkpoint.activated = activated;
+
+                if let Some(material) = materials.get_mut(material) {
+                    material.emissive = checkpoint.emissive();
+                }
+            }
+        });
+    });
+
+    info!("Quick loaded");
+}
+
+/// A quick save only makes sense for the level it was taken in.
+fn clear_quick_save(mut commands: Commands) {
+    commands.remove_resource::<QuickSave>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("cosmic-junkyard-{}-{}", name, std::process::id()))
+            .join(PROGRESS_FILE_NAME)
+    }
+
+    #[test]
+    fn progress_is_replaced_whole() {
+        let path = test_path("replaced");
+        let written = Mutex::new(0);
+
+        write_progress(&path, "first", 1, &written).unwrap();
+        write_progress(&path, "second", 2, &written).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert!(!path.with_extension("ron.tmp").exists());
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn stale_progress_is_dropped() {
+        let path = test_path("stale");
+        let written = Mutex::new(0);
+
+        // The later save finishing first
+        write_progress(&path, "second", 2, &written).unwrap();
+        write_progress(&path, "first", 1, &written).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(*written.lock().unwrap(), 2);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}